@@ -0,0 +1,814 @@
+//! Converts the message catalogs translators work with, Fluent, gettext PO, and flat JSON, into
+//! the source of Polly components, so they can be used as the locales of a template.
+//!
+//! Every message becomes a component taking each variable it uses as an argument, in the order
+//! they first appear, and plurals become `&std.plural` calls.
+use std::collections::BTreeMap;
+
+use serde_json;
+use serde_json::Value;
+
+use plural::{gettext_categories, OTHER};
+
+/// Characters that have to be escaped to be written as text in Polly, which are every character
+/// the lexer takes as a symbol.
+const SPECIAL: &'static str = "@&$/\\{}|()[].,=\"'#*";
+/// The key of a JSON plural, or select naming the variable it selects on.
+const SELECT: &'static str = "$select";
+
+/// A piece of a message.
+#[derive(Clone, Debug)]
+enum Part {
+    Text(String),
+    Variable(String),
+    /// Picks one of the cases by the value of the variable.
+    Cases(String, Vec<(String, Vec<Part>)>),
+}
+
+/// Converts a Fluent file into Polly components. Messages, their attributes, which become
+/// components such as `login.placeholder`, variables, string literals, terms, and select
+/// expressions are supported, while functions, and references to other messages aren't.
+pub fn fluent(_lang: &str, source: &str) -> Result<String, String> {
+    let mut entries: Vec<(String, String, usize)> = Vec::new();
+    let mut message: Option<String> = None;
+
+    for (number, line) in source.lines().enumerate() {
+        let number = number + 1;
+        if line.starts_with('#') {
+            continue;
+        } else if line.trim().is_empty() {
+            if let Some(entry) = entries.last_mut() {
+                entry.1.push('\n');
+            }
+        } else if line.starts_with(char::is_whitespace) || line.starts_with('}') {
+            let trimmed = line.trim_left();
+            if trimmed.starts_with('.') {
+                let (attribute, value) = match split_entry(&trimmed[1..]) {
+                    Some(entry) => entry,
+                    None => return Err(format!("line {}: expected `.attribute = value`", number)),
+                };
+                match message {
+                    Some(ref message) => {
+                        entries.push((format!("{}.{}", message, attribute), value, number))
+                    }
+                    None => return Err(format!("line {}: attribute outside of a message", number)),
+                }
+            } else {
+                match entries.last_mut() {
+                    Some(entry) => {
+                        entry.1.push('\n');
+                        entry.1.push_str(line);
+                    }
+                    None => {
+                        return Err(format!("line {}: indented line outside of a message", number))
+                    }
+                }
+            }
+        } else {
+            match split_entry(line) {
+                Some((id, value)) => {
+                    message = Some(id.clone());
+                    entries.push((id, value, number));
+                }
+                None => return Err(format!("line {}: expected `message = value`", number)),
+            }
+        }
+    }
+
+    let mut terms = BTreeMap::new();
+    let mut output = String::new();
+    for (id, value, number) in entries {
+        let mut parser = FluentParser {
+            chars: dedent(&value).chars().collect(),
+            position: 0,
+            terms: &terms,
+        };
+        let parts = match parser.pattern(false) {
+            Ok(parts) => trim(parts),
+            Err(error) => return Err(format!("line {}: {}", number, error)),
+        };
+
+        if id.starts_with('-') {
+            terms.insert(id[1..].to_owned(), parts);
+        } else if !parts.is_empty() {
+            if let Err(error) = write_component(&id, &parts, &mut output) {
+                return Err(format!("line {}: {}", number, error));
+            }
+        }
+    }
+    Ok(output)
+}
+
+/// Splits `id = value` into its id, and value.
+fn split_entry(line: &str) -> Option<(String, String)> {
+    line.find('=').map(|index| {
+        (line[..index].trim().to_owned(), line[index + 1..].trim_left().to_owned())
+    })
+}
+
+/// Removes the indentation shared by every line after the first.
+fn dedent(value: &str) -> String {
+    let mut lines = value.lines();
+    let first = lines.next().unwrap_or("");
+    let rest: Vec<&str> = lines.collect();
+    let indent = rest.iter()
+                     .filter(|line| !line.trim().is_empty())
+                     .map(|line| line.len() - line.trim_left().len())
+                     .min()
+                     .unwrap_or(0);
+
+    let mut dedented = String::from(first);
+    for line in rest {
+        dedented.push('\n');
+        if line.len() >= indent {
+            dedented.push_str(&line[indent..]);
+        }
+    }
+    dedented
+}
+
+struct FluentParser<'a> {
+    chars: Vec<char>,
+    position: usize,
+    terms: &'a BTreeMap<String, Vec<Part>>,
+}
+
+impl<'a> FluentParser<'a> {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).cloned()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().map_or(false, char::is_whitespace) {
+            self.position += 1;
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(c) if c == expected => {
+                self.position += 1;
+                Ok(())
+            }
+            Some(c) => Err(format!("expected `{}`, found `{}`", expected, c)),
+            None => Err(format!("expected `{}`", expected)),
+        }
+    }
+
+    fn identifier(&mut self) -> String {
+        let mut identifier = String::new();
+        while let Some(c) = self.peek() {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                identifier.push(c);
+                self.position += 1;
+            } else {
+                break;
+            }
+        }
+        identifier
+    }
+
+    /// Reads text, and placeables. The pattern of a variant ends at the line starting the next
+    /// variant, or closing the select expression.
+    fn pattern(&mut self, variant: bool) -> Result<Vec<Part>, String> {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+
+        while let Some(c) = self.peek() {
+            if c == '{' {
+                self.position += 1;
+                if !text.is_empty() {
+                    parts.push(Part::Text(text.clone()));
+                    text.clear();
+                }
+                match self.placeable() {
+                    Ok(placeable) => parts.extend(placeable),
+                    Err(error) => return Err(error),
+                }
+            } else if variant && c == '\n' && self.at_variant_end() {
+                break;
+            } else {
+                text.push(c);
+                self.position += 1;
+            }
+        }
+
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+        Ok(parts)
+    }
+
+    fn at_variant_end(&self) -> bool {
+        self.chars[self.position..]
+            .iter()
+            .find(|c| !c.is_whitespace())
+            .map_or(true, |&c| c == '[' || c == '*' || c == '}')
+    }
+
+    fn placeable(&mut self) -> Result<Vec<Part>, String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('$') => {
+                self.position += 1;
+                let variable = self.identifier();
+                self.skip_whitespace();
+                if self.peek() == Some('-') {
+                    self.position += 1;
+                    if let Err(error) = self.expect('>') {
+                        return Err(error);
+                    }
+                    return self.select(variable).map(|part| vec![part]);
+                }
+                self.expect('}').map(|_| vec![Part::Variable(variable)])
+            }
+            Some('-') => {
+                self.position += 1;
+                let term = self.identifier();
+                let parts = match self.terms.get(&term) {
+                    Some(parts) => parts.clone(),
+                    None => return Err(format!("no term named `-{}`", term)),
+                };
+                self.expect('}').map(|_| parts)
+            }
+            Some('"') => {
+                self.position += 1;
+                let mut literal = String::new();
+                loop {
+                    match self.peek() {
+                        Some('"') => break,
+                        Some('\\') => {
+                            self.position += 1;
+                            if let Some(c) = self.peek() {
+                                literal.push(c);
+                            }
+                        }
+                        Some(c) => literal.push(c),
+                        None => return Err(String::from("unclosed string literal")),
+                    }
+                    self.position += 1;
+                }
+                self.position += 1;
+                self.expect('}').map(|_| vec![Part::Text(literal)])
+            }
+            _ => {
+                let identifier = self.identifier();
+                self.skip_whitespace();
+                if self.peek() == Some('(') {
+                    Err(format!("functions, such as `{}`, aren't supported", identifier))
+                } else {
+                    Err(format!("references to other messages, such as `{}`, aren't supported",
+                                identifier))
+                }
+            }
+        }
+    }
+
+    fn select(&mut self, variable: String) -> Result<Part, String> {
+        let mut cases = Vec::new();
+        let mut default = None;
+
+        loop {
+            self.skip_whitespace();
+            let is_default = match self.peek() {
+                Some('}') => {
+                    self.position += 1;
+                    break;
+                }
+                Some('*') => {
+                    self.position += 1;
+                    true
+                }
+                _ => false,
+            };
+
+            if let Err(error) = self.expect('[') {
+                return Err(error);
+            }
+            let mut key = String::new();
+            while let Some(c) = self.peek() {
+                self.position += 1;
+                if c == ']' {
+                    break;
+                }
+                key.push(c);
+            }
+            let key = key.trim().to_owned();
+
+            let parts = match self.pattern(true) {
+                Ok(parts) => trim(parts),
+                Err(error) => return Err(error),
+            };
+            if is_default {
+                default = Some((key.clone(), parts.clone()));
+            }
+            cases.push((key, parts));
+        }
+
+        match default {
+            Some((ref key, ref parts)) if key != OTHER => {
+                if !cases.iter().any(|&(ref key, _)| key == OTHER) {
+                    cases.push((String::from(OTHER), parts.clone()));
+                }
+            }
+            Some(_) => {}
+            None => {
+                return Err(String::from("select expressions need a default variant, marked \
+                                         with `*`"))
+            }
+        }
+        Ok(Part::Cases(variable, cases))
+    }
+}
+
+/// Converts a gettext PO file into Polly components. Each entry is named by its `msgctxt`, or
+/// if it doesn't have one, its `msgid`. Variables are written in braces, such as `{name}`, with
+/// `{{`, and `}}` being literal braces. The forms of a plural are matched to the plural categories
+/// of the locale, and select on the first variable of `msgid_plural`, or `count` if it doesn't
+/// have any. Untranslated, and fuzzy entries are left out, so the locale falls back to another.
+pub fn gettext(lang: &str, source: &str) -> Result<String, String> {
+    let mut output = String::new();
+    let mut entry = PoEntry::default();
+
+    for (number, line) in source.lines().enumerate() {
+        let number = number + 1;
+        let line = line.trim();
+
+        if line.is_empty() {
+            if let Err(error) = entry.write(lang, &mut output) {
+                return Err(format!("line {}: {}", number, error));
+            }
+            entry = PoEntry::default();
+            continue;
+        } else if line.starts_with("#,") {
+            entry.fuzzy = entry.fuzzy || line.contains("fuzzy");
+            continue;
+        } else if line.starts_with('#') {
+            continue;
+        }
+
+        let (keyword, string) = match line.find(char::is_whitespace) {
+            Some(index) if !line.starts_with('"') => (&line[..index], line[index..].trim()),
+            _ => ("", line),
+        };
+        let string = match unquote(string) {
+            Some(string) => string,
+            None => return Err(format!("line {}: expected a quoted string", number)),
+        };
+
+        let field = match keyword {
+            "" => entry.last.clone(),
+            "msgctxt" => Field::Context,
+            "msgid" => Field::Id,
+            "msgid_plural" => Field::Plural,
+            "msgstr" => Field::Str(0),
+            keyword if keyword.starts_with("msgstr[") && keyword.ends_with(']') => {
+                match keyword[7..keyword.len() - 1].parse() {
+                    Ok(index) => Field::Str(index),
+                    Err(_) => return Err(format!("line {}: invalid plural form", number)),
+                }
+            }
+            keyword => return Err(format!("line {}: unknown keyword `{}`", number, keyword)),
+        };
+        if keyword.is_empty() && field == Field::None {
+            return Err(format!("line {}: string outside of an entry", number));
+        }
+
+        match field {
+            Field::Context => entry.context.get_or_insert(String::new()).push_str(&string),
+            Field::Id => entry.id.push_str(&string),
+            Field::Plural => entry.plural.get_or_insert(String::new()).push_str(&string),
+            Field::Str(index) => {
+                entry.strings.entry(index).or_insert(String::new()).push_str(&string)
+            }
+            Field::None => {}
+        }
+        entry.last = field;
+    }
+
+    match entry.write(lang, &mut output) {
+        Ok(()) => Ok(output),
+        Err(error) => Err(error),
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Field {
+    None,
+    Context,
+    Id,
+    Plural,
+    Str(usize),
+}
+
+impl Default for Field {
+    fn default() -> Self {
+        Field::None
+    }
+}
+
+#[derive(Default)]
+struct PoEntry {
+    context: Option<String>,
+    id: String,
+    plural: Option<String>,
+    strings: BTreeMap<usize, String>,
+    fuzzy: bool,
+    last: Field,
+}
+
+impl PoEntry {
+    fn write(&self, lang: &str, output: &mut String) -> Result<(), String> {
+        // The entry without an id is the header.
+        if self.id.is_empty() || self.fuzzy || self.strings.values().all(String::is_empty) {
+            return Ok(());
+        }
+        let name = self.context.as_ref().unwrap_or(&self.id);
+
+        let parts = match self.plural {
+            None => {
+                match placeholders(self.strings.get(&0).map_or("", |string| &**string)) {
+                    Ok(parts) => parts,
+                    Err(error) => return Err(error),
+                }
+            }
+            Some(ref plural) => {
+                let variable = match placeholders(plural) {
+                    Ok(parts) => first_variable(&parts),
+                    Err(error) => return Err(error),
+                };
+                let variable = match placeholders(&self.id) {
+                    Ok(parts) => variable.or_else(|| first_variable(&parts)),
+                    Err(error) => return Err(error),
+                };
+                let categories = gettext_categories(lang);
+                let mut cases = Vec::new();
+                for (&index, string) in &self.strings {
+                    let parts = match placeholders(string) {
+                        Ok(parts) => parts,
+                        Err(error) => return Err(error),
+                    };
+                    // The last form covers every number the others don't.
+                    let category = if index + 1 == self.strings.len() {
+                        OTHER
+                    } else {
+                        match categories.get(index) {
+                            Some(category) => category,
+                            None => continue,
+                        }
+                    };
+                    cases.push((String::from(category), parts));
+                }
+                let variable = variable.or_else(|| {
+                    cases.iter().filter_map(|&(_, ref parts)| first_variable(parts)).next()
+                });
+                match variable {
+                    Some(variable) => vec![Part::Cases(variable, cases)],
+                    None => {
+                        return Err(format!("`{}` is a plural without a variable to count by",
+                                           name))
+                    }
+                }
+            }
+        };
+
+        write_component(name, &parts, output)
+    }
+}
+
+/// Reads a string in double quotes, with C style escapes.
+fn unquote(string: &str) -> Option<String> {
+    if string.len() < 2 || !string.starts_with('"') || !string.ends_with('"') {
+        return None;
+    }
+
+    let mut unquoted = String::new();
+    let mut chars = string[1..string.len() - 1].chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('n') => unquoted.push('\n'),
+                Some('t') => unquoted.push('\t'),
+                Some('r') => unquoted.push('\r'),
+                Some(c) => unquoted.push(c),
+                None => return None,
+            }
+        } else {
+            unquoted.push(c);
+        }
+    }
+    Some(unquoted)
+}
+
+/// Converts a flat JSON object into Polly components. Each key is the name of a component, and
+/// its value a message, with variables written in braces such as `{name}`. An object of messages
+/// is a plural, or select, with the keys being the cases. It selects on the variable named by its
+/// `"$select"` key, or else the first variable in its messages. Messages that are `null` are left
+/// out.
+pub fn json(_lang: &str, source: &str) -> Result<String, String> {
+    let catalog = match serde_json::from_str(source) {
+        Ok(Value::Object(catalog)) => catalog,
+        Ok(_) => return Err(String::from("the catalog has to be a JSON object")),
+        Err(error) => return Err(error.to_string()),
+    };
+
+    let mut output = String::new();
+    for (name, message) in catalog {
+        let parts = match message {
+            Value::String(ref message) => placeholders(message),
+            Value::Object(ref messages) => {
+                let mut cases = Vec::new();
+                let mut variable = match messages.get(SELECT) {
+                    Some(&Value::String(ref variable)) if is_name(variable) => {
+                        Some(variable.clone())
+                    }
+                    Some(_) => return Err(format!("`{}.{}` isn't a variable name", name, SELECT)),
+                    None => None,
+                };
+                for (case, message) in messages.iter().filter(|&(case, _)| *case != SELECT) {
+                    let parts = match message.as_string().map(placeholders) {
+                        Some(Ok(parts)) => parts,
+                        Some(Err(error)) => return Err(format!("`{}`: {}", name, error)),
+                        None => return Err(format!("`{}.{}` isn't a string", name, case)),
+                    };
+                    variable = variable.or_else(|| first_variable(&parts));
+                    cases.push((case.clone(), parts));
+                }
+                match variable {
+                    Some(variable) => Ok(vec![Part::Cases(variable, cases)]),
+                    None => {
+                        return Err(format!("`{}` has no variable to select on, name it with `{}`",
+                                           name,
+                                           SELECT))
+                    }
+                }
+            }
+            Value::Null => continue,
+            _ => return Err(format!("`{}` isn't a string, or an object", name)),
+        };
+
+        let result = parts.and_then(|parts| write_component(&name, &parts, &mut output));
+        if let Err(error) = result {
+            return Err(format!("`{}`: {}", name, error));
+        }
+    }
+    Ok(output)
+}
+
+/// Splits a message into its text, and the variables written in braces, such as `{name}`.
+fn placeholders(message: &str) -> Result<Vec<Part>, String> {
+    let mut parts = Vec::new();
+    let mut text = String::new();
+    let mut chars = message.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                let _ = chars.next();
+                text.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                let _ = chars.next();
+                text.push('}');
+            }
+            '{' => {
+                let mut variable = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => variable.push(c),
+                        None => return Err(String::from("unclosed `{`")),
+                    }
+                }
+                if !text.is_empty() {
+                    parts.push(Part::Text(text.clone()));
+                    text.clear();
+                }
+                parts.push(Part::Variable(variable.trim().to_owned()));
+            }
+            c => text.push(c),
+        }
+    }
+
+    if !text.is_empty() {
+        parts.push(Part::Text(text));
+    }
+    Ok(parts)
+}
+
+fn first_variable(parts: &[Part]) -> Option<String> {
+    let mut variables = Vec::new();
+    variables_of(parts, &mut variables);
+    variables.into_iter().next()
+}
+
+/// Collects the variables used by `parts`, in the order they first appear.
+fn variables_of(parts: &[Part], variables: &mut Vec<String>) {
+    for part in parts {
+        match *part {
+            Part::Text(_) => {}
+            Part::Variable(ref variable) => {
+                if !variables.contains(variable) {
+                    variables.push(variable.clone());
+                }
+            }
+            Part::Cases(ref variable, ref cases) => {
+                if !variables.contains(variable) {
+                    variables.push(variable.clone());
+                }
+                for &(_, ref parts) in cases {
+                    variables_of(parts, variables);
+                }
+            }
+        }
+    }
+}
+
+/// Removes the whitespace at the start, and end of a pattern.
+fn trim(mut parts: Vec<Part>) -> Vec<Part> {
+    if let Some(&mut Part::Text(ref mut text)) = parts.first_mut() {
+        *text = text.trim_left().to_owned();
+    }
+    if let Some(&mut Part::Text(ref mut text)) = parts.last_mut() {
+        *text = text.trim_right().to_owned();
+    }
+    parts.into_iter()
+         .filter(|part| {
+             match *part {
+                 Part::Text(ref text) => !text.is_empty(),
+                 _ => true,
+             }
+         })
+         .collect()
+}
+
+/// Whether `name` can be written as the name of a component, or variable.
+fn is_name(name: &str) -> bool {
+    !name.is_empty() && !name.starts_with('.') && !name.ends_with('.') &&
+    !name.contains("..") &&
+    name.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_' || c == '.')
+}
+
+/// Writes the definition of a component named `name`, taking every variable in `parts`.
+fn write_component(name: &str, parts: &[Part], output: &mut String) -> Result<(), String> {
+    if !is_name(name) {
+        return Err(format!("`{}` can't be the name of a component", name));
+    }
+
+    let mut variables = Vec::new();
+    variables_of(parts, &mut variables);
+    if let Some(variable) = variables.iter().find(|variable| !is_name(variable)) {
+        return Err(format!("`{}` can't be the name of a variable", variable));
+    }
+
+    output.push('&');
+    output.push_str(name);
+    if !variables.is_empty() {
+        let parameters: Vec<String> = variables.iter().map(|name| format!("@{}", name)).collect();
+        output.push_str(&format!("({})", parameters.join(", ")));
+    }
+    output.push_str(" {");
+    match write_parts(parts, output) {
+        Ok(()) => {
+            output.push_str("}\n");
+            Ok(())
+        }
+        Err(error) => Err(error),
+    }
+}
+
+fn write_parts(parts: &[Part], output: &mut String) -> Result<(), String> {
+    let mut after_variable = false;
+    for part in parts {
+        match *part {
+            Part::Text(ref text) => {
+                for (index, c) in text.chars().enumerate() {
+                    // Text straight after a variable would otherwise carry on its name, or path,
+                    // so it's separated with a "\".
+                    let separate = index == 0 && after_variable && !c.is_whitespace();
+                    if separate || SPECIAL.contains(c) {
+                        output.push('\\');
+                    }
+                    output.push(c);
+                }
+                after_variable = false;
+            }
+            Part::Variable(ref variable) => {
+                output.push('@');
+                output.push_str(variable);
+                after_variable = true;
+            }
+            Part::Cases(ref variable, ref cases) => {
+                let is_plural = cases.iter().any(|&(ref case, _)| {
+                    is_category(case) || case.parse::<f64>().is_ok()
+                });
+                let function = if is_plural { "std.plural" } else { "std.select" };
+                output.push_str(&format!("&{}(@{}) {{", function, variable));
+                for &(ref case, ref parts) in cases {
+                    if !is_name(case) {
+                        return Err(format!("`{}` can't be the name of a case", case));
+                    }
+                    output.push_str(&format!("&{} {{", case));
+                    if let Err(error) = write_parts(parts, output) {
+                        return Err(error);
+                    }
+                    output.push('}');
+                }
+                output.push('}');
+                after_variable = false;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn is_category(case: &str) -> bool {
+    ["zero", "one", "two", "few", "many"].contains(&case)
+}
+
+#[allow(dead_code, unused_imports)]
+mod tests {
+    use super::{fluent, gettext, json};
+
+    #[test]
+    fn fluent_messages() {
+        let source = r#"
+# A comment
+-brand = Polly
+hello = Hello { $name }, welcome to { -brand }!
+items = { $count ->
+    [0] No items
+    [one] One item
+   *[other] { $count } items
+}
+login = Log in
+    .placeholder = Email, or @username
+"#;
+        assert_eq!(fluent("en", source).unwrap(),
+                   "&hello(@name) {Hello @name\\, welcome to Polly!}\n&items(@count) \
+                    {&std.plural(@count) {&0 {No items}&one {One item}&other {@count \
+                    items}}}\n&login {Log in}\n&login.placeholder {Email\\, or \\@username}\n");
+        assert!(fluent("en", "hello = { NUMBER($count) }").is_err());
+    }
+
+    #[test]
+    fn gettext_messages() {
+        let source = r#"
+msgid ""
+msgstr "Plural-Forms: nplurals=3;\n"
+
+msgctxt "greeting"
+msgid "Hello {name}!"
+msgstr "Привет, {name}!"
+
+msgid "files"
+msgid_plural "{count} files"
+msgstr[0] "{count} файл"
+msgstr[1] "{count} файла"
+msgstr[2] "{count} "
+"файлов"
+
+#, fuzzy
+msgid "draft"
+msgstr "Черновик"
+
+msgid "untranslated"
+msgstr ""
+"#;
+        assert_eq!(gettext("ru", source).unwrap(),
+                   "&greeting(@name) {Привет\\, @name\\!}\n&files(@count) \
+                    {&std.plural(@count) {&one {@count файл}&few {@count файла}&other \
+                    {@count файлов}}}\n");
+
+        let uncounted = "msgid \"file\"\nmsgid_plural \"files\"\nmsgstr[0] \"Файл\"\n\
+                         msgstr[1] \"Файлы\"\n";
+        assert!(gettext("ru", uncounted).is_err());
+    }
+
+    #[test]
+    fn json_messages() {
+        let source = r#"{
+            "nav.home": "Home/{user}",
+            "items": {"one": "One item", "other": "{n} items"},
+            "gender": {"$select": "gender", "female": "She", "other": "They"},
+            "missing": null
+        }"#;
+        assert_eq!(json("en", source).unwrap(),
+                   "&gender(@gender) {&std.select(@gender) {&female {She}&other {They}}}\n\
+                    &items(@n) {&std.plural(@n) {&one {One item}&other {@n items}}}\n\
+                    &nav.home(@user) {Home\\/@user}\n");
+        assert!(json("en", r#"{"gender": {"female": "She", "other": "They"}}"#).is_err());
+        assert!(json("en", r#"{"gender": {"$select": "a b", "other": "They"}}"#).is_err());
+    }
+
+    #[test]
+    fn symbols_after_placeholders() {
+        let expected = "&title(@page, @site) {@page \\| @site \\(\\#1\\)}\n";
+
+        assert_eq!(fluent("en", "title = { $page } | { $site } (#1)").unwrap(), expected);
+        assert_eq!(gettext("en", "msgid \"title\"\nmsgstr \"{page} | {site} (#1)\"\n").unwrap(),
+                   expected);
+        assert_eq!(json("en", r#"{"title": "{page} | {site} (#1)"}"#).unwrap(), expected);
+    }
+}
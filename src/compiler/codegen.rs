@@ -1,18 +1,26 @@
-use std::collections::BTreeMap;
-use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
 use std::error;
 use std::fmt;
 use std::io;
+use std::io::Write;
 use std::rc::Rc;
 use std::string;
+use std::sync::Arc;
 
 use serde_json::Value;
 use super::*;
-use template::Template;
+use plural::{plural_category, OTHER};
+use template::{CompiledTemplate, LOCALES_PREFIX};
 
 const VOID_ELEMENTS: [&'static str; 13] = ["area", "base", "br", "col", "hr", "img", "input",
                                            "link", "meta", "command", "keygen", "source",
                                            "!DOCTYPE"];
+/// The name of the slot holding the children passed to a component.
+const CHILDREN: &'static str = "children";
+/// Renders the case matching the plural category of a number, such as `&one { .. }`.
+const PLURAL: &'static str = "std.plural";
+/// Renders the case matching a value, such as `&female { .. }`.
+const SELECT: &'static str = "std.select";
 pub type CodegenResult = Result<String, CodegenError>;
 
 macro_rules! html_try {
@@ -23,266 +31,634 @@ macro_rules! html_try {
     }
 }
 
-pub struct Codegen {
-    elements: Vec<AstResult>,
-    variables: BTreeMap<String, Value>,
-    parent: Rc<RefCell<Template>>,
+/// Generates the HTML of an AST, in a single locale, with the variables in scope. A `Codegen` is
+/// also passed to every `PollyFn` so that functions can render components.
+pub struct Codegen<'a> {
+    template: &'a CompiledTemplate,
+    lang: &'a str,
+    variables: &'a BTreeMap<String, Value>,
+    /// The components passed into the component being rendered.
+    components: Rc<HashMap<String, Arc<Component>>>,
+    /// The markup passed into the component being rendered.
+    slots: Rc<HashMap<String, Rc<Slot<'a>>>>,
 }
 
-impl Codegen {
-    pub fn new(ast: Vec<AstResult>,
-               json: BTreeMap<String, Value>,
-               parent: Rc<RefCell<Template>>)
+/// Markup passed into a component by its caller. It is rendered with the variables, and
+/// components that were in scope where it was written, rather than the component's. The scope is
+/// shared with the caller, rather than copied.
+struct Slot<'a> {
+    ast: Vec<AstResult>,
+    variables: &'a BTreeMap<String, Value>,
+    components: Rc<HashMap<String, Arc<Component>>>,
+    slots: Rc<HashMap<String, Rc<Slot<'a>>>>,
+}
+
+impl<'a> Codegen<'a> {
+    /// Creates a `Codegen` rendering `template` in the locale `lang`, with `variables` in scope.
+    pub fn new(template: &'a CompiledTemplate,
+               lang: &'a str,
+               variables: &'a BTreeMap<String, Value>)
                -> Self {
         Codegen {
-            elements: ast,
-            variables: json,
-            parent: parent,
+            template: template,
+            lang: lang,
+            variables: variables,
+            components: Rc::new(HashMap::new()),
+            slots: Rc::new(HashMap::new()),
         }
     }
 
-    pub fn render_component(ast: Vec<AstResult>,
-                            json: BTreeMap<String, Value>,
-                            parent: Rc<RefCell<Template>>)
-                            -> CodegenResult {
-        Codegen {
-            elements: ast,
-            variables: json,
-            parent: parent,
-        }
-        .generate_html()
+    /// The locale being rendered.
+    pub fn lang(&self) -> &str {
+        self.lang
     }
 
-    pub fn generate_html(&mut self) -> CodegenResult {
-        let mut html = String::new();
+    /// Renders the AST into a HTML String.
+    pub fn generate_html(&self, ast: &[AstResult]) -> CodegenResult {
+        let mut html = Vec::new();
+
+        if let Err(error) = self.write_html(ast, &mut html) {
+            return Err(error);
+        }
 
-        for element in &self.elements {
-            match self.render(element) {
-                Ok(string) => html.push_str(&*string),
-                Err(error) => return Err(error),
+        match String::from_utf8(html) {
+            Ok(html) => Ok(html),
+            Err(error) => Err(CodegenError::FromUtf8Error(error)),
+        }
+    }
+
+    /// Renders the AST as HTML, writing it directly into `writer`, as it is generated.
+    pub fn write_html(&self, ast: &[AstResult], writer: &mut Write) -> Result<(), CodegenError> {
+        for element in ast {
+            if let Err(error) = self.render(element, writer) {
+                return Err(error);
             }
         }
-        Ok(html)
+        Ok(())
     }
 
-    pub fn call_component(component: &Component,
-                          arg_map: Option<BTreeMap<String, Value>>,
-                          parent: &Rc<RefCell<Template>>)
+    /// Renders a component, with `variables` being the only variables in scope.
+    pub fn call_component(&self,
+                          component: &Component,
+                          variables: BTreeMap<String, Value>)
                           -> CodegenResult {
-        if let Some(arg_map) = arg_map {
-            Codegen {
-                elements: component.ast(),
-                variables: arg_map,
-                parent: parent.clone(),
+        Codegen::new(self.template, self.lang, &variables).generate_html(component.ast())
+    }
+
+    /// Renders a call to a component, with `children` being the markup passed to it.
+    fn generate_from_component(&self,
+                               component_call: &ComponentCall,
+                               children: &[AstResult],
+                               writer: &mut Write)
+                               -> Result<(), CodegenError> {
+        let span = component_call.span();
+
+        if let Some(slot) = self.slots.get(component_call.name()) {
+            if !component_call.values().is_empty() {
+                return Err(CodegenError::WrongNumberOfArguments(0,
+                                                                component_call.values().len(),
+                                                                span.clone()));
             }
-        } else {
-            Codegen {
-                elements: component.ast(),
-                variables: BTreeMap::new(),
-                parent: parent.clone(),
+            return Codegen {
+                       template: self.template,
+                       lang: self.lang,
+                       variables: slot.variables,
+                       components: slot.components.clone(),
+                       slots: slot.slots.clone(),
+                   }
+                   .write_html(&slot.ast, writer);
+        }
+
+        if component_call.name() == PLURAL || component_call.name() == SELECT {
+            return self.generate_from_cases(component_call, children, writer);
+        }
+
+        let component = match self.get_component(component_call.name()) {
+            Some(component) => component,
+            None => {
+                let error = CodegenError::NoSuchComponent(String::from(component_call.name()),
+                                                          span.clone());
+                // A locale missing one of its components leaves a gap in the page, rather than
+                // breaking it, unless the template is strict.
+                if component_call.name().starts_with(LOCALES_PREFIX) {
+                    return self.ignore_unless_strict(error);
+                }
+                // The children of a component called without any are empty.
+                if component_call.name() == CHILDREN {
+                    return Ok(());
+                }
+                return Err(error);
+            }
+        };
+
+        // Calls without arguments, such as `&header { .. }`, directly inside the children fill
+        // the parameter of the same name, the rest of the children fill `&children`.
+        let mut slots = HashMap::new();
+        let mut rest = Vec::new();
+        for child in children {
+            if let Ok(Token::CompCall(ref call)) = *child {
+                let is_parameter = component.args().contains(&ArgKey::Comp(call.name().into()));
+                if call.values().is_empty() && is_parameter {
+                    slots.insert(String::from(call.name()),
+                                 Rc::new(self.slot(call.children().to_vec())));
+                    continue;
+                }
             }
+            rest.push(child.clone());
         }
-        .generate_html()
-    }
 
-    fn generate_from_component(&self, component_call: ComponentCall) -> CodegenResult {
-        let parent = self.parent.borrow();
-        if let Some(component) = parent.get_component(component_call.name()) {
-            let args = component.args();
-            let arg_values = component_call.values();
-            let mut arg_map = BTreeMap::new();
-            if args.len() == arg_values.len() {
-                let zipped = args.iter().zip(arg_values.iter());
-
-                for (ref arg, ref value) in zipped {
-
-                    match **arg {
-                        ArgKey::Json(ref arg_name) => {
-                            if let ArgKey::Json(ref arg_value) = **value {
-                                let value = match self.get_variable(arg_value) {
-                                    Ok(value) => value,
-                                    Err(error) => return Err(error),
-                                };
-                                arg_map.insert(arg_name.clone(), value);
-                            }
-                        }
-                        ArgKey::Comp(ref name) => {
-                            return Err(CodegenError::CompPassedToComp(name.clone()))
-                        }
+        let params: Vec<ArgKey> = component.args()
+                                           .into_iter()
+                                           .filter(|arg| !slots.contains_key(&arg.value()))
+                                           .collect();
+        let named = component_call.named();
+
+        for name in named.keys() {
+            if !params.iter().any(|param| param.value() == *name) {
+                return Err(CodegenError::UnknownArgument(name.clone(), span.clone()));
+            }
+        }
+
+        // Parameters that aren't passed by name are passed the positional arguments in order,
+        // falling back to their default value.
+        let mut positional = component_call.values().iter();
+        let mut arg_map = BTreeMap::new();
+        let mut components = HashMap::new();
+        for param in &params {
+            let name = param.value();
+            let value = match named.get(&name).or_else(|| positional.next()) {
+                Some(value) => value.clone(),
+                None => {
+                    match component.defaults().get(&name) {
+                        Some(default) => ArgKey::Literal(default.clone()),
+                        None => return Err(CodegenError::MissingArgument(name, span.clone())),
                     }
                 }
-                Codegen::render_component(component.ast(), arg_map, self.parent.clone())
-            } else {
-                Err(CodegenError::WrongNumberOfArguments(args.len(), arg_values.len()))
+            };
+
+            match (param, &value) {
+                (&ArgKey::Json(ref arg_name), &ArgKey::Json(ref variable)) => {
+                    match self.get_variable(variable, span) {
+                        Ok(value) => arg_map.insert(arg_name.clone(), value),
+                        Err(error) => return Err(error),
+                    };
+                }
+                (&ArgKey::Json(ref arg_name), &ArgKey::Literal(ref literal)) => {
+                    arg_map.insert(arg_name.clone(), literal.clone());
+                }
+                (&ArgKey::Comp(ref arg_name), &ArgKey::Comp(ref name)) => {
+                    if let Some(slot) = self.slots.get(name) {
+                        slots.insert(arg_name.clone(), slot.clone());
+                        continue;
+                    }
+                    if name == CHILDREN && self.get_component(name).is_none() {
+                        slots.insert(arg_name.clone(), Rc::new(self.slot(Vec::new())));
+                        continue;
+                    }
+                    match self.get_component(name) {
+                        Some(value) => components.insert(arg_name.clone(), value),
+                        None => {
+                            return Err(CodegenError::NoSuchComponent(name.clone(), span.clone()))
+                        }
+                    };
+                }
+                (arg, _) => return Err(CodegenError::WrongArgumentType(arg.value(), span.clone())),
             }
-        } else {
-            Err(CodegenError::NoSuchComponent(String::from(component_call.name())))
         }
+
+        if positional.len() > 0 {
+            let given = component_call.values().len();
+            return Err(CodegenError::WrongNumberOfArguments(given - positional.len(),
+                                                            given,
+                                                            span.clone()));
+        }
+
+        if !rest.is_empty() && !slots.contains_key(CHILDREN) && !components.contains_key(CHILDREN) {
+            slots.insert(String::from(CHILDREN), Rc::new(self.slot(rest)));
+        }
+
+        Codegen {
+            template: self.template,
+            lang: self.lang,
+            variables: &arg_map,
+            components: Rc::new(components),
+            slots: Rc::new(slots),
+        }
+        .write_html(component.ast(), writer)
     }
 
-    fn render_element(&self, element: &Element) -> CodegenResult {
-        use std::io::Write;
-        let mut html = Vec::new();
-        let tag = element.tag();
-        html_try!(write!(&mut html, "<{}", tag));
+    /// Renders the children of the case matching the value passed to `&std.plural`, or
+    /// `&std.select`, such as `&few { .. }`. Plurals first look for a case named after the number
+    /// itself, such as `&0 { .. }`, then its plural category in the current locale. Both fall
+    /// back to `&other { .. }`.
+    fn generate_from_cases(&self,
+                           call: &ComponentCall,
+                           children: &[AstResult],
+                           writer: &mut Write)
+                           -> Result<(), CodegenError> {
+        let span = call.span();
+        if call.values().len() != 1 || !call.named().is_empty() {
+            return Err(CodegenError::WrongNumberOfArguments(1,
+                                                            call.values().len() +
+                                                            call.named().len(),
+                                                            span.clone()));
+        }
 
-        if !element.classes().is_empty() {
+        let value = match call.values()[0] {
+            ArgKey::Json(ref variable) => {
+                match self.get_variable(variable, span) {
+                    Ok(value) => value,
+                    Err(error) => return Err(error),
+                }
+            }
+            ArgKey::Literal(ref literal) => literal.clone(),
+            ArgKey::Comp(ref name) => {
+                return Err(CodegenError::WrongArgumentType(name.clone(), span.clone()))
+            }
+        };
 
-            html_try!(write!(&mut html, " class=\""));
-            let mut classes_iter = element.classes().iter();
-            html_try!(write!(&mut html, "{}", classes_iter.next().unwrap()));
+        let mut names = vec![value_to_string(&value)];
+        if call.name() == PLURAL {
+            if let Some(category) = plural_category(self.lang, &value) {
+                names.push(String::from(category));
+            }
+        }
+        names.push(String::from(OTHER));
 
-            for class in classes_iter {
-                if !class.is_empty() {
-                    html_try!(write!(&mut html, " {}", &*class));
+        let mut cases = Vec::new();
+        for child in children {
+            if let Ok(Token::CompCall(ref case)) = *child {
+                if case.values().is_empty() {
+                    cases.push(case);
                 }
             }
-            html_try!(write!(&mut html, "\""));
         }
 
-        if !element.attributes().is_empty() {
-            for (key, value) in element.attributes() {
-                if !key.is_empty() {
-                    if !value.is_empty() {
-                        html_try!(write!(&mut html, " {}=\"{}\"", key, value));
-                    } else {
-                        html_try!(write!(&mut html, " {}", key));
+        for name in &names {
+            if let Some(case) = cases.iter().find(|case| case.name() == name) {
+                return self.write_html(case.children(), writer);
+            }
+        }
+
+        let error = CodegenError::MissingCase(names[names.len() - 2].clone(), span.clone());
+        self.ignore_unless_strict(error)
+    }
+
+    /// Creates a slot holding `ast`, sharing everything currently in scope.
+    fn slot(&self, ast: Vec<AstResult>) -> Slot<'a> {
+        Slot {
+            ast: ast,
+            variables: self.variables,
+            components: self.components.clone(),
+            slots: self.slots.clone(),
+        }
+    }
+
+    /// Gets a component, looking in the components passed in as arguments first, and then in
+    /// the template.
+    fn get_component(&self, name: &str) -> Option<Arc<Component>> {
+        match self.components.get(name) {
+            Some(component) => Some(component.clone()),
+            None => self.template.get_component(name, self.lang).cloned(),
+        }
+    }
+
+    fn render_element(&self, element: &Element, html: &mut Write) -> Result<(), CodegenError> {
+        let tag = element.tag();
+        html_try!(write!(html, "<{}", tag));
+
+        let mut classes = Vec::new();
+        for class in element.classes() {
+            match self.render_attribute_value("class", class) {
+                Ok(Some(class)) => {
+                    if !class.is_empty() {
+                        classes.push(class);
                     }
                 }
+                Ok(None) => {}
+                Err(error) => return Err(error),
             }
         }
 
-        html_try!(write!(&mut html, ">"));
+        if !classes.is_empty() {
+            html_try!(write!(html, " class=\"{}\"", classes.join(" ")));
+        }
 
+        for (key, value) in element.attributes() {
+            if key.is_empty() {
+                continue;
+            }
+            // Quoted keys such as the ones in a DOCTYPE are written out with their quotes.
+            let escaped_key = if key.len() > 1 && key.starts_with('"') && key.ends_with('"') {
+                format!("\"{}\"", escape_attribute(&key[1..key.len() - 1]))
+            } else {
+                escape_attribute(key)
+            };
 
-        for void in &VOID_ELEMENTS {
-            if void == &tag {
-                return match String::from_utf8(html) {
-                    Ok(html) => Ok(html),
-                    Err(error) => Err(CodegenError::FromUtf8Error(error)),
-                };
+            match self.render_attribute_value(key, value) {
+                Ok(Some(ref value)) if !value.is_empty() => {
+                    html_try!(write!(html, " {}=\"{}\"", escaped_key, value));
+                }
+                Ok(Some(_)) => html_try!(write!(html, " {}", escaped_key)),
+                Ok(None) => {}
+                Err(error) => return Err(error),
             }
         }
 
-        if let Some(ref resource) = *element.resource() {
-            match self.generate_from_component(resource.clone()) {
-                Ok(rendered) => html_try!(write!(&mut html, "{}", rendered)),
-                Err(err) => return Err(err),
-            }
+        html_try!(write!(html, ">"));
 
 
-        } else {
-            for child in element.children() {
-                match self.render(child) {
-                    Ok(rendered) => {
-                        html_try!(write!(&mut html, "{}", rendered));
-                    }
-                    Err(error) => return Err(error),
-                }
+        if VOID_ELEMENTS.contains(&tag) {
+            return Ok(());
+        }
+
+        if let Some(ref resource) = *element.resource() {
+            if let Err(error) = self.generate_from_component(resource, element.children(), html) {
+                return Err(error);
             }
+        } else if let Err(error) = self.write_html(element.children(), html) {
+            return Err(error);
         }
 
-        html_try!(write!(&mut html, "</{}>", tag));
+        html_try!(write!(html, "</{}>", tag));
+        Ok(())
+    }
 
-        match String::from_utf8(html) {
-            Ok(html) => Ok(html),
-            Err(error) => Err(CodegenError::FromUtf8Error(error)),
+    /// Renders the escaped value of an attribute. Returns `None` if the attribute shouldn't be
+    /// rendered at all, which is the case for variables that are `false`, or `null`.
+    fn render_attribute_value(&self,
+                              key: &str,
+                              value: &AttributeValue)
+                              -> Result<Option<String>, CodegenError> {
+        let variable = match *value {
+            AttributeValue::Text(ref text) => return Ok(Some(escape_attribute(text))),
+            AttributeValue::Variable(ref variable) => variable,
+        };
+
+        let value = match self.variable_value(variable) {
+            Ok(value) => value,
+            Err(error) => return Err(error),
+        };
+
+        match value {
+            Value::Null | Value::Bool(false) => Ok(None),
+            Value::Bool(true) => Ok(Some(String::new())),
+            // Raw values are still sanitised, as a trusted URL has no need for an unsafe scheme.
+            ref value if is_url_attribute(key) => {
+                let url = sanitise_url(&value_to_string(value));
+
+                if variable.is_raw() {
+                    Ok(Some(url))
+                } else {
+                    Ok(Some(escape_attribute(&url)))
+                }
+            }
+            ref value if variable.is_raw() => Ok(Some(value_to_string(value))),
+            ref value => Ok(Some(escape_attribute(&value_to_string(value)))),
         }
     }
 
-    fn render_function(&self, function: &FunctionCall) -> CodegenResult {
+    fn render_function(&self,
+                       function: &FunctionCall,
+                       writer: &mut Write)
+                       -> Result<(), CodegenError> {
         let mut arguments: BTreeMap<String, ArgValue> = BTreeMap::new();
 
         for (key, value) in function.args().clone() {
 
             match value {
                 ArgKey::Json(id) => {
-                    let real_value = match self.get_variable(&id) {
+                    let real_value = match self.get_variable(&id, function.span()) {
                         Ok(value) => value,
                         Err(error) => return Err(error),
                     };
                     arguments.insert(key, ArgValue::Json(Some(real_value)));
                 }
+                ArgKey::Literal(value) => {
+                    arguments.insert(key, ArgValue::Json(Some(value)));
+                }
                 ArgKey::Comp(id) => {
-                    let parent = self.parent.borrow();
-                    let real_value = match parent.get_component(&id) {
-                        Some(value) => Some(value.clone()),
-                        None => None,
-                    };
-                    arguments.insert(key, ArgValue::Comp(real_value));
+                    arguments.insert(key, ArgValue::Comp(self.get_component(&id)));
                 }
             }
         }
 
-        let parent = self.parent.borrow();
-        if let Some(fun) = parent.get_function(function.identifier()) {
-            match fun(arguments, &self.parent) {
-                Ok(string) => Ok(string),
-                Err(error) => Err(CodegenError::FunctionError(error)),
+        if let Some(fun) = self.template.get_function(function.identifier()) {
+            match fun(arguments, self) {
+                Ok(html) => write_str(&html, writer),
+                Err(error) => Err(CodegenError::FunctionError(error, function.span().clone())),
             }
         } else {
-            Err(CodegenError::NoSuchFunction(String::from(function.identifier())))
+            Err(CodegenError::NoSuchFunction(String::from(function.identifier()),
+                                             function.span().clone()))
         }
     }
 
-    fn get_variable(&self, name: &String) -> Result<Value, CodegenError> {
-        let segments: Vec<&str> = name.split('.').collect();
+    /// Gets the value at the path `name`, such as `items[0].name`. Anything missing along the
+    /// path is `null`, but a path into a string, number, or boolean is an error.
+    fn get_variable(&self, name: &str, span: &Span) -> Result<Value, CodegenError> {
+        match self.find_variable(name, span) {
+            Ok(Some(value)) => Ok(value),
+            Ok(None) => {
+                let error = CodegenError::UndefinedVariable(String::from(name), span.clone());
+                self.ignore_unless_strict(error).map(|_| Value::Null)
+            }
+            Err(error) => Err(error),
+        }
+    }
 
-        if segments.len() == 1 {
-            match self.variables.get(name) {
-                Some(value) => Ok(value.clone()),
-                None => Ok(Value::String(String::new())),
+    /// Gets the value of a variable, passed through each of its filters. A variable that isn't
+    /// defined isn't an error if it's passed to a filter that handles undefined variables, such
+    /// as `default`.
+    fn variable_value(&self, variable: &Variable) -> Result<Value, CodegenError> {
+        let template = self.template;
+        let handles_undefined = variable.filters()
+                                        .iter()
+                                        .any(|filter| template.handles_undefined(filter.name()));
+        let mut value = if handles_undefined {
+            match self.find_variable(variable.name(), variable.span()) {
+                Ok(value) => value.unwrap_or(Value::Null),
+                Err(error) => return Err(error),
             }
         } else {
-            match Value::Object(self.variables.clone()).find_path(&segments) {
-                Some(value) => return Ok(value.clone()),
-                _ => return Err(CodegenError::NotAnObjectOrNull(String::from(name.clone()))),
+            match self.get_variable(variable.name(), variable.span()) {
+                Ok(value) => value,
+                Err(error) => return Err(error),
+            }
+        };
 
+        for filter in variable.filters() {
+            let mut arguments = Vec::new();
+            for argument in filter.args() {
+                match *argument {
+                    ArgKey::Json(ref id) => {
+                        match self.get_variable(id, filter.span()) {
+                            Ok(value) => arguments.push(value),
+                            Err(error) => return Err(error),
+                        }
+                    }
+                    ArgKey::Literal(ref literal) => arguments.push(literal.clone()),
+                    ArgKey::Comp(ref id) => {
+                        return Err(CodegenError::WrongArgumentType(id.clone(),
+                                                                   filter.span().clone()))
+                    }
+                }
             }
+
+            value = match self.template.get_filter(filter.name()) {
+                Some(fun) => {
+                    match fun(value, &arguments) {
+                        Ok(value) => value,
+                        Err(error) => {
+                            return Err(CodegenError::FilterError(error, filter.span().clone()))
+                        }
+                    }
+                }
+                None => {
+                    return Err(CodegenError::NoSuchFilter(String::from(filter.name()),
+                                                          filter.span().clone()))
+                }
+            };
         }
+
+        Ok(value)
     }
 
-    fn render(&self, token: &AstResult) -> CodegenResult {
+    /// Looks up the value at the path `name`, returning `None` if it isn't defined.
+    fn find_variable(&self, name: &str, span: &Span) -> Result<Option<Value>, CodegenError> {
+        let mut segments = path_segments(name).into_iter();
+        let mut value = match segments.next() {
+            Some(Segment::Key(ref key)) => self.variables.get(key),
+            _ => None,
+        };
+
+        for segment in segments {
+            value = match (value, segment) {
+                (None, _) | (Some(&Value::Null), _) => None,
+                (Some(&Value::Object(ref object)), Segment::Key(key)) => object.get(&key),
+                (Some(&Value::Object(ref object)), Segment::Index(index)) => {
+                    object.get(&index.to_string())
+                }
+                (Some(&Value::Array(ref array)), Segment::Key(key)) => {
+                    key.parse().ok().and_then(|index: usize| array.get(index))
+                }
+                (Some(&Value::Array(ref array)), Segment::Index(index)) => {
+                    let index = if index < 0 { array.len() as i64 + index } else { index };
+                    if index < 0 { None } else { array.get(index as usize) }
+                }
+                (Some(_), _) => {
+                    return Err(CodegenError::NotAnObjectOrNull(String::from(name), span.clone()))
+                }
+            };
+        }
+
+        Ok(value.cloned())
+    }
+
+    /// Returns `error` if the template is strict, otherwise it's passed to the template's
+    /// warning callback, and rendering carries on.
+    fn ignore_unless_strict(&self, error: CodegenError) -> Result<(), CodegenError> {
+        if self.template.strict() {
+            Err(error)
+        } else {
+            self.template.warn(&error);
+            Ok(())
+        }
+    }
+
+    fn render(&self, token: &AstResult, writer: &mut Write) -> Result<(), CodegenError> {
         use super::Token::*;
         match *token {
-            Ok(Html(ref element)) => self.render_element(element),
-            Ok(Text(ref text)) => Ok(text.clone()),
+            Ok(Html(ref element)) => self.render_element(element, writer),
+            Ok(Text(ref text)) => write_str(text, writer),
             Ok(Variable(ref variable)) => {
-                match self.get_variable(variable) {
-                    Ok(value) => Ok(value_to_string(&value)),
+                match self.variable_value(variable) {
+                    Ok(ref value) if variable.is_raw() => {
+                        write_str(&value_to_string(value), writer)
+                    }
+                    Ok(ref value) => write_str(&escape_html(&value_to_string(value)), writer),
                     Err(error) => Err(error),
                 }
             }
             Ok(CompCall(ref component_call)) => {
-                self.generate_from_component(component_call.clone())
+                self.generate_from_component(component_call, component_call.children(), writer)
+            }
+            Ok(Function(ref function)) => self.render_function(function, writer),
+            Ok(Comment(ref comment)) => {
+                if self.template.html_comments() {
+                    write_str(&format!("<!-- {} -->", escape_comment(comment)), writer)
+                } else {
+                    Ok(())
+                }
             }
-            Ok(Function(ref function)) => self.render_function(function),
             Err(ref error) => Err(CodegenError::AstError(error.clone())),
         }
     }
 }
 
+fn write_str(text: &str, writer: &mut Write) -> Result<(), CodegenError> {
+    html_try!(writer.write_all(text.as_bytes()));
+    Ok(())
+}
+
 #[derive(Debug)]
 /// Errors generated during code generation
 pub enum CodegenError {
     /// Errors during AST parsing.
     AstError(AstError),
-    /// Component passed to a component.
-    CompPassedToComp(String),
     /// Error converting to a UTF8 String.
     FromUtf8Error(string::FromUtf8Error),
+    /// An error generated by a filter.
+    FilterError(String, Span),
     /// An error generated by a Polly Function.
-    FunctionError(String),
+    FunctionError(String, Span),
     /// Error generated from writing to a buffer.
     IoError(io::Error),
+    /// A parameter without a default value wasn't passed an argument.
+    MissingArgument(String, Span),
+    /// `&std.plural`, or `&std.select` has no case for the value, nor an `&other` case.
+    MissingCase(String, Span),
     /// No such component in the template.
-    NoSuchComponent(String),
+    NoSuchComponent(String, Span),
+    /// No such filter in the template.
+    NoSuchFilter(String, Span),
     /// No such function in the template.
-    NoSuchFunction(String),
-    /// JSON wasn't an object, or it was Null.
-    NotAnObjectOrNull(String),
+    NoSuchFunction(String, Span),
+    /// The path of a variable went into JSON that isn't an object, array, or null.
+    NotAnObjectOrNull(String, Span),
+    /// A variable that isn't in the JSON, in a strict template.
+    UndefinedVariable(String, Span),
+    /// An argument was passed by a name that isn't one of the component's parameters.
+    UnknownArgument(String, Span),
+    /// A component was passed to a parameter taking JSON, or JSON to a parameter taking a
+    /// component.
+    WrongArgumentType(String, Span),
     /// Wrong number of arguments passed in.
-    WrongNumberOfArguments(usize, usize),
+    WrongNumberOfArguments(usize, usize, Span),
+}
+
+impl CodegenError {
+    /// Where in the source the error occurred, if it came from the template itself.
+    pub fn span(&self) -> Option<&Span> {
+        use self::CodegenError::*;
+
+        match *self {
+            AstError(ref error) => error.span(),
+            FilterError(_, ref span) |
+            FunctionError(_, ref span) |
+            MissingArgument(_, ref span) |
+            MissingCase(_, ref span) |
+            NoSuchComponent(_, ref span) |
+            NoSuchFilter(_, ref span) |
+            NoSuchFunction(_, ref span) |
+            NotAnObjectOrNull(_, ref span) |
+            UndefinedVariable(_, ref span) |
+            UnknownArgument(_, ref span) |
+            WrongArgumentType(_, ref span) |
+            WrongNumberOfArguments(_, _, ref span) => Some(span),
+            FromUtf8Error(_) | IoError(_) => None,
+        }
+    }
 }
 
 impl error::Error for CodegenError {
@@ -291,16 +667,22 @@ impl error::Error for CodegenError {
 
         match *self {
             AstError(ref error) => error.description(),
-            CompPassedToComp(_) => {
-                "Currently you cannot cannot pass a component to another component: "
-            }
             FromUtf8Error(ref error) => error.description(),
-            FunctionError(_) => "Function produced error: ",
+            FilterError(_, _) => "Filter produced error: ",
+            FunctionError(_, _) => "Function produced error: ",
             IoError(ref error) => error.description(),
-            NoSuchComponent(_) => "Component called doesn't exist in the current template: ",
-            NoSuchFunction(_) => "Function called doesn't exist in the current template: ",
-            NotAnObjectOrNull(_) => "JSON passed in wasn't an object, or was null: ",
-            WrongNumberOfArguments(_, _) => "Incorrect number of arguments passed in: ",
+            MissingArgument(_, _) => "Component called without an argument it requires: ",
+            MissingCase(_, _) => "No case matches the value, and there's no other case: ",
+            NoSuchComponent(_, _) => "Component called doesn't exist in the current template: ",
+            NoSuchFilter(_, _) => "Filter used doesn't exist in the current template: ",
+            NoSuchFunction(_, _) => "Function called doesn't exist in the current template: ",
+            NotAnObjectOrNull(_, _) => "Variable's path goes into JSON that isn't an object: ",
+            UndefinedVariable(_, _) => "Variable isn't defined in the JSON: ",
+            UnknownArgument(_, _) => "Component called with an argument it doesn't have: ",
+            WrongArgumentType(_, _) => {
+                "Argument passed in doesn't match the type of the component's parameter: "
+            }
+            WrongNumberOfArguments(_, _, _) => "Incorrect number of arguments passed in: ",
         }
     }
 }
@@ -312,18 +694,29 @@ impl fmt::Display for CodegenError {
 
         let msg = match *self {
             AstError(ref error) => error.to_string(),
-            CompPassedToComp(ref name) => format!("{} NAME: {}", self.description(), name),
             FromUtf8Error(ref error) => error.to_string(),
-            FunctionError(ref error) => format!("{} ERROR: {}", self.description(), error),
+            FilterError(ref error, ref span) |
+            FunctionError(ref error, ref span) => {
+                format!("{} ERROR: {} AT: {}", self.description(), error, span)
+            }
             IoError(ref error) => error.to_string(),
-            NoSuchComponent(ref name) | NoSuchFunction(ref name) | NotAnObjectOrNull(ref name) => {
-                format!("{} NAME: {}", self.description(), name)
+            MissingArgument(ref name, ref span) |
+            MissingCase(ref name, ref span) |
+            NoSuchComponent(ref name, ref span) |
+            NoSuchFilter(ref name, ref span) |
+            NoSuchFunction(ref name, ref span) |
+            NotAnObjectOrNull(ref name, ref span) |
+            UndefinedVariable(ref name, ref span) |
+            UnknownArgument(ref name, ref span) |
+            WrongArgumentType(ref name, ref span) => {
+                format!("{} NAME: {} AT: {}", self.description(), name, span)
             }
-            WrongNumberOfArguments(expected, actual) => {
-                format!("{} EXPECTED: {} ACTUAL: {}",
+            WrongNumberOfArguments(expected, actual, ref span) => {
+                format!("{} EXPECTED: {} ACTUAL: {} AT: {}",
                         self.description(),
                         expected,
-                        actual)
+                        actual,
+                        span)
             }
         };
 
@@ -331,7 +724,9 @@ impl fmt::Display for CodegenError {
     }
 }
 
-fn value_to_string(value: &Value) -> String {
+/// Converts JSON to the text it's rendered as. Arrays, and objects have each of their values
+/// rendered one after another.
+pub fn value_to_string(value: &Value) -> String {
     use serde_json::Value;
     match *value {
         Value::Null => String::new(),
@@ -0,0 +1,75 @@
+/// Attributes whose values are URLs, and are checked for unsafe schemes when interpolated.
+const URL_ATTRIBUTES: [&'static str; 6] = ["action", "cite", "formaction", "href", "poster",
+                                           "src"];
+/// URL schemes that are allowed in interpolated URLs, relative URLs are always allowed.
+const SAFE_SCHEMES: [&'static str; 5] = ["ftp", "http", "https", "mailto", "tel"];
+/// The URL that replaces any URL with an unsafe scheme, such as "javascript:".
+pub const UNSAFE_URL: &'static str = "about:invalid#polly";
+
+/// Escapes text so that it can be safely placed inside of an element, replacing any characters
+/// that HTML would otherwise interpret as markup with their entities.
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            ch => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+/// Escapes text so that it can be safely placed inside of a quoted attribute value, or used as
+/// an attribute's name.
+pub fn escape_attribute(text: &str) -> String {
+    // Older versions of Internet Explorer treat backticks as quotes around attribute values.
+    escape_html(text).replace('`', "&#96;")
+}
+
+/// Escapes the text of a comment, so that it can't end the HTML comment it's placed in early.
+pub fn escape_comment(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for ch in text.chars() {
+        // Splitting up every pair of dashes means "-->", and "--!>" can never appear.
+        if ch == '-' && escaped.ends_with('-') {
+            escaped.push(' ');
+        }
+        escaped.push(ch);
+    }
+    escaped
+}
+
+/// Whether the attribute's value is interpreted as a URL by the browser.
+pub fn is_url_attribute(key: &str) -> bool {
+    URL_ATTRIBUTES.contains(&&*key.to_lowercase())
+}
+
+/// Replaces URLs that use a scheme that could execute code, such as "javascript:", or "data:"
+/// with `UNSAFE_URL`. Relative URLs, and URLs using a scheme in `SAFE_SCHEMES` are returned
+/// unchanged.
+pub fn sanitise_url(url: &str) -> String {
+    // Browsers ignore whitespace, and control characters within the scheme, so "java\tscript:"
+    // is still treated as "javascript:".
+    let stripped: String = url.chars()
+                              .filter(|ch| !ch.is_whitespace() && !ch.is_control())
+                              .collect();
+
+    let scheme_end = match stripped.find(|ch| ch == ':' || ch == '/' || ch == '?' || ch == '#') {
+        Some(index) if stripped[index..].starts_with(':') => index,
+        _ => return url.to_owned(),
+    };
+
+    let scheme = stripped[..scheme_end].to_lowercase();
+
+    if SAFE_SCHEMES.contains(&&*scheme) {
+        url.to_owned()
+    } else {
+        String::from(UNSAFE_URL)
+    }
+}
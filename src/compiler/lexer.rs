@@ -1,18 +1,51 @@
 use std::iter::Peekable;
+use std::mem;
+use std::path::{Path, PathBuf};
 use std::str::CharIndices;
+use std::sync::Arc;
 
 use super::*;
 use super::Lexeme::*;
 use super::Operator::*;
 /// Lexer
 pub struct Lexer<'a> {
+    source: &'a str,
     input: Peekable<CharIndices<'a>>,
     output: Vec<Lexeme>,
+    file: Option<Arc<PathBuf>>,
+    /// The line of the next character.
+    line: usize,
+    /// The column of the next character.
+    col: usize,
+    /// The byte offset just after the last character taken.
+    position: usize,
+    /// The last character taken.
+    previous: Option<char>,
+    /// Whether the last lexeme was a comment with whitespace before it.
+    spaced_comment: bool,
+    /// How many parentheses deep the lexer is in an attributes field, or argument list. Quotes
+    /// only start string literals inside of them, so they can be used freely in text.
+    arguments_depth: usize,
+    /// How many brackets deep the lexer is in an index into a variable. Quotes start string
+    /// literals inside of them as well.
+    index_depth: usize,
 }
 
 impl<'a> Lexer<'a> {
     fn take(&mut self) -> Option<(usize, char)> {
-        self.input.next()
+        let next = self.input.next();
+
+        if let Some((index, character)) = next {
+            self.position = index + character.len_utf8();
+            self.previous = Some(character);
+            if character == '\n' {
+                self.line += 1;
+                self.col = 1;
+            } else {
+                self.col += 1;
+            }
+        }
+        next
     }
 
     fn peek(&mut self) -> Option<&(usize, char)> {
@@ -28,11 +61,24 @@ impl<'a> Lexer<'a> {
         self.output
     }
 
-    /// TODO
-    pub fn new(input: &'a str) -> Self {
+    /// Lexes the source of `file`, so that the spans of every lexeme point into that file.
+    pub fn with_file<P: AsRef<Path>>(input: &'a str, file: P) -> Self {
+        Lexer::lex(input, Some(Arc::new(file.as_ref().to_path_buf())))
+    }
+
+    fn lex(input: &'a str, file: Option<Arc<PathBuf>>) -> Self {
         let mut lexer = Lexer {
+            source: input,
             input: input.char_indices().peekable(),
             output: Vec::new(),
+            file: file,
+            line: 1,
+            col: 1,
+            position: 0,
+            previous: None,
+            spaced_comment: false,
+            arguments_depth: 0,
+            index_depth: 0,
         };
 
         while let Some(token) = lexer.take_token() {
@@ -42,6 +88,11 @@ impl<'a> Lexer<'a> {
         lexer
     }
 
+    /// Creates a span from `start` to the end of the last character taken.
+    fn span(&self, start: usize, line: usize, col: usize) -> Span {
+        Span::new(self.file.clone(), start, self.position, line, col)
+    }
+
     fn take_token(&mut self) -> Option<Lexeme> {
         let mut leading_space = false;
         while let Some(&(_, character)) = self.peek() {
@@ -53,24 +104,69 @@ impl<'a> Lexer<'a> {
             }
         }
 
-        match self.take() {
-            Some((index, AMPERSAND)) => Some(Symbol(index, Ampersand)),
-            Some((index, AT)) => Some(Symbol(index, At)),
-            Some((index, BACKSLASH)) => Some(Symbol(index, BackSlash)),
-            Some((index, COMMA)) => Some(Symbol(index, Comma)),
-            Some((index, CLOSEBRACE)) => Some(Symbol(index, CloseBrace)),
-            Some((index, CLOSEPARAM)) => Some(Symbol(index, CloseParam)),
-            Some((index, DOLLAR)) => Some(Symbol(index, Dollar)),
-            Some((index, DOT)) => Some(Symbol(index, Dot)),
-            Some((index, DOUBLEQUOTE)) => Some(Symbol(index, Quote)),
-            Some((index, EQUALS)) => Some(Symbol(index, Equals)),
-            Some((index, FORWARDSLASH)) => Some(Symbol(index, ForwardSlash)),
-            Some((index, OPENBRACE)) => Some(Symbol(index, OpenBrace)),
-            Some((index, OPENPARAM)) => Some(Symbol(index, OpenParam)),
-            Some((index, POUND)) => Some(Symbol(index, Pound)),
-            Some((index, STAR)) => Some(Symbol(index, Star)),
+        // A comment with whitespace on either side of it is removed along with the whitespace on
+        // one side, so "Hello /* comment */ World" still has a single space between its words.
+        let spaced_comment = mem::replace(&mut self.spaced_comment, false);
+        let (line, col) = (self.line, self.col);
+        let previous = self.previous;
+        let operator = match self.take() {
+            Some((_, AMPERSAND)) => Ampersand,
+            Some((_, AT)) => At,
+            Some((_, BACKSLASH)) => BackSlash,
+            Some((_, COMMA)) => Comma,
+            Some((_, CLOSEBRACE)) => CloseBrace,
+            Some((_, CLOSEBRACKET)) if self.index_depth > 0 => {
+                self.index_depth -= 1;
+                CloseBracket
+            }
+            Some((_, CLOSEPARAM)) => {
+                if self.arguments_depth > 0 {
+                    self.arguments_depth -= 1;
+                }
+                CloseParam
+            }
+            Some((_, DOLLAR)) => Dollar,
+            Some((_, DOT)) => Dot,
+            Some((index, quote @ DOUBLEQUOTE)) |
+            Some((index, quote @ SINGLEQUOTE)) if self.arguments_depth > 0 ||
+                                                   self.index_depth > 0 => {
+                return Some(self.string_literal(quote, index, line, col));
+            }
+            Some((_, DOUBLEQUOTE)) => Quote,
+            Some((_, EQUALS)) => Equals,
+            Some((index, FORWARDSLASH)) => {
+                let next = self.peek().map(|&(_, ch)| ch);
+                // Comments can't directly follow a word, so that paths such as "image/*", and
+                // "http://" are left alone.
+                let after_word = previous.map_or(false, |ch| is_word_character(ch));
+                let after_space = previous.map_or(true, char::is_whitespace);
+
+                if next == Some(STAR) && !after_word {
+                    self.spaced_comment = after_space;
+                    return Some(self.block_comment(index, line, col));
+                } else if next == Some(FORWARDSLASH) && after_space {
+                    self.spaced_comment = after_space;
+                    return Some(self.line_comment(index, line, col));
+                } else {
+                    ForwardSlash
+                }
+            }
+            Some((_, OPENBRACE)) => OpenBrace,
+            Some((_, OPENBRACKET)) if !leading_space && self.opens_index() => {
+                self.index_depth += 1;
+                OpenBracket
+            }
+            Some((_, OPENPARAM)) => {
+                if self.arguments_depth > 0 || self.opens_arguments() {
+                    self.arguments_depth += 1;
+                }
+                OpenParam
+            }
+            Some((_, PIPE)) => Pipe,
+            Some((_, POUND)) => Pound,
+            Some((_, STAR)) => Star,
             Some((index, character)) => {
-                let mut word = if leading_space {
+                let mut word = if leading_space && !spaced_comment {
                     ' '.to_string()
                 } else {
                     String::new()
@@ -83,9 +179,8 @@ impl<'a> Lexer<'a> {
                         // The following case is for determining if a character divides words or
                         // if it is packaged with the words. So things like "Hello}" comes out
                         // as Text: "Hello" Operator: "}"
-                        ch if !ch.is_alphanumeric() && !ch.is_whitespace() && ch != '-' &&
-                              ch != '_' => {
-                            return Some(Word(index, word));
+                        ch if !is_word_character(ch) && !ch.is_whitespace() => {
+                            return Some(Word(self.span(index, line, col), word));
                         }
                         ch => {
                             if !ch.is_whitespace() {
@@ -106,130 +201,339 @@ impl<'a> Lexer<'a> {
                         let _ = self.take();
                     }
                 }
-                Some(Word(index, word))
+                return Some(Word(self.span(index, line, col), word));
+            }
+            None => return None,
+        };
+
+        // Every operator is a single ASCII character.
+        let start = self.position - 1;
+        Some(Symbol(self.span(start, line, col), operator))
+    }
+
+    /// Whether a ( just taken starts an attributes field, or argument list. That's the case when
+    /// it directly follows the name of an element, component, function, or filter, or a class, or
+    /// id.
+    fn opens_arguments(&self) -> bool {
+        let mut lexemes = self.output.iter().rev();
+        match (lexemes.next(), lexemes.next()) {
+            (Some(&Word(_, ref name)), Some(&Symbol(_, ref operator))) => {
+                let named = match *operator {
+                    Ampersand | Dollar | Dot | ForwardSlash | Pipe | Pound => true,
+                    _ => false,
+                };
+                named && !name.ends_with(char::is_whitespace)
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether a [ just taken indexes into a variable, such as `@items[0]`, rather than
+    /// being part of the text. That's the case when it directly follows the path of a variable.
+    fn opens_index(&self) -> bool {
+        let mut lexemes = self.output.iter().rev();
+        match lexemes.next() {
+            Some(&Word(_, ref name)) if !name.ends_with(char::is_whitespace) => {}
+            Some(&Symbol(_, CloseBracket)) => {}
+            _ => return false,
+        }
+
+        for lexeme in lexemes {
+            match *lexeme {
+                Symbol(_, At) => return true,
+                Symbol(_, Dot) | Symbol(_, OpenBracket) | Symbol(_, CloseBracket) | Str(..) => {}
+                Word(_, ref name) if !name.ends_with(char::is_whitespace) => {}
+                _ => return false,
+            }
+        }
+        false
+    }
+
+    /// Takes a string literal opened by `quote` at `start`, replacing its escapes. A string that
+    /// is never closed is only its opening quote, and the input after the quote is lexed as if
+    /// the quote wasn't there, so the parser can report it without losing the rest of the file.
+    fn string_literal(&mut self, quote: char, start: usize, line: usize, col: usize) -> Lexeme {
+        let after_quote = (self.input.clone(), self.line, self.col, self.position, self.previous);
+        let mut string = String::new();
+        let mut closed = false;
+
+        while let Some((_, character)) = self.take() {
+            match character {
+                ch if ch == quote => {
+                    closed = true;
+                    break;
+                }
+                BACKSLASH => {
+                    match self.take() {
+                        Some((_, 'n')) => string.push('\n'),
+                        Some((_, 'r')) => string.push('\r'),
+                        Some((_, 't')) => string.push('\t'),
+                        Some((_, 'u')) => string.push_str(&self.unicode_escape()),
+                        Some((_, ch @ BACKSLASH)) |
+                        Some((_, ch @ DOUBLEQUOTE)) |
+                        Some((_, ch @ SINGLEQUOTE)) => string.push(ch),
+                        // Unknown escapes are left as they were written.
+                        Some((_, ch)) => {
+                            string.push(BACKSLASH);
+                            string.push(ch);
+                        }
+                        None => string.push(BACKSLASH),
+                    }
+                }
+                ch => string.push(ch),
+            }
+        }
+
+        if !closed {
+            let (input, line, col, position, previous) = after_quote;
+            self.input = input;
+            self.line = line;
+            self.col = col;
+            self.position = position;
+            self.previous = previous;
+            string.clear();
+        }
+
+        let source = String::from(&self.source[start..self.position]);
+        Str(self.span(start, line, col), string, source)
+    }
+
+    /// Takes the `{..}` of a `\u{..}` escape, returning the character it stands for. If it isn't
+    /// a valid escape, the text of it is returned instead.
+    fn unicode_escape(&mut self) -> String {
+        let mut text = String::from("\\u");
+        if self.peek().map(|&(_, ch)| ch) != Some(OPENBRACE) {
+            return text;
+        }
+        let _ = self.take();
+        text.push(OPENBRACE);
+
+        let mut hex = String::new();
+        while let Some(&(_, character)) = self.peek() {
+            if character == CLOSEBRACE || !character.is_digit(16) || hex.len() == 6 {
+                break;
+            }
+            hex.push(character);
+            let _ = self.take();
+        }
+        text.push_str(&hex);
+
+        if self.peek().map(|&(_, ch)| ch) != Some(CLOSEBRACE) {
+            return text;
+        }
+        let _ = self.take();
+        text.push(CLOSEBRACE);
+
+        match u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+            Some(character) => character.to_string(),
+            None => text,
+        }
+    }
+
+    /// Takes a comment running from the `/*` starting at `start`, until `*/`, or the end of the
+    /// input.
+    fn block_comment(&mut self, start: usize, line: usize, col: usize) -> Lexeme {
+        let _ = self.take();
+        let mut comment = String::new();
+
+        while let Some((_, character)) = self.take() {
+            if character == STAR && self.peek().map(|&(_, ch)| ch) == Some(FORWARDSLASH) {
+                let _ = self.take();
+                break;
+            }
+            comment.push(character);
+        }
+
+        Comment(self.span(start, line, col), comment.trim().to_owned())
+    }
+
+    /// Takes a comment running from the `//` starting at `start`, until the end of the line.
+    fn line_comment(&mut self, start: usize, line: usize, col: usize) -> Lexeme {
+        let _ = self.take();
+        let mut comment = String::new();
+
+        while let Some(&(_, character)) = self.peek() {
+            if character == '\n' {
+                break;
             }
-            None => None,
+            comment.push(character);
+            let _ = self.take();
         }
+
+        Comment(self.span(start, line, col), comment.trim().to_owned())
     }
 }
 
-#[allow(unused_imports)]
+/// Whether the character can be part of a word, rather than dividing words.
+fn is_word_character(character: char) -> bool {
+    character.is_alphanumeric() || character == '-' || character == '_'
+}
+
+#[allow(dead_code, unused_imports)]
 mod tests {
     use super::Lexer;
-    use compiler::tokens::Lexeme;
-    use compiler::tokens::Lexeme::{Word, Symbol};
+    use compiler::tokens::{Lexeme, Operator, Span};
+    use compiler::tokens::Lexeme::{Comment, Str, Word, Symbol};
     use compiler::tokens::Operator::*;
 
+    /// A symbol on the first line of source that didn't come from a file.
+    fn symbol_at(index: usize, operator: Operator) -> Lexeme {
+        Symbol(Span::new(None, index, index + 1, 1, index + 1), operator)
+    }
+
+    /// A word on the first line of source that didn't come from a file.
+    fn word_at(index: usize, word: &str) -> Lexeme {
+        let end = index + word.trim_left().len();
+        Word(Span::new(None, index, end, 1, index + 1), word.to_owned())
+    }
+
     #[test]
     fn ignore_spaces() {
-        let lexer = Lexer::new(" \t\n   ");
+        let lexer = Lexer::lex(" \t\n   ", None);
 
         assert_eq!(lexer.output(), vec![]);
     }
 
     #[test]
     fn ampersand_operator() {
-        let lexer = Lexer::new("&");
+        let lexer = Lexer::lex("&", None);
 
-        assert_eq!(lexer.output(), vec![Symbol(0, Ampersand)]);
+        assert_eq!(lexer.output(), vec![symbol_at(0, Ampersand)]);
     }
 
     #[test]
     fn at_operator() {
-        let lexer = Lexer::new("@");
+        let lexer = Lexer::lex("@", None);
 
-        assert_eq!(lexer.output(), vec![Symbol(0, At)]);
+        assert_eq!(lexer.output(), vec![symbol_at(0, At)]);
     }
     #[test]
     fn back_slash_operator() {
-        let lexer = Lexer::new("\\");
+        let lexer = Lexer::lex("\\", None);
 
-        assert_eq!(lexer.output(), vec![Symbol(0, BackSlash)]);
+        assert_eq!(lexer.output(), vec![symbol_at(0, BackSlash)]);
     }
     #[test]
     fn close_brace_operator() {
-        let lexer = Lexer::new("}");
+        let lexer = Lexer::lex("}", None);
 
-        assert_eq!(lexer.output(), vec![Symbol(0, CloseBrace)]);
+        assert_eq!(lexer.output(), vec![symbol_at(0, CloseBrace)]);
     }
     #[test]
     fn close_param_operator() {
-        let lexer = Lexer::new(")");
+        let lexer = Lexer::lex(")", None);
 
-        assert_eq!(lexer.output(), vec![Symbol(0, CloseParam)]);
+        assert_eq!(lexer.output(), vec![symbol_at(0, CloseParam)]);
     }
     #[test]
     fn dollar_operator() {
-        let lexer = Lexer::new("$");
+        let lexer = Lexer::lex("$", None);
 
-        assert_eq!(lexer.output(), vec![Symbol(0, Dollar)]);
+        assert_eq!(lexer.output(), vec![symbol_at(0, Dollar)]);
     }
     #[test]
     fn dot_operator() {
-        let lexer = Lexer::new(".");
+        let lexer = Lexer::lex(".", None);
 
-        assert_eq!(lexer.output(), vec![Symbol(0, Dot)]);
+        assert_eq!(lexer.output(), vec![symbol_at(0, Dot)]);
     }
     #[test]
     fn equals_operator() {
-        let lexer = Lexer::new("=");
+        let lexer = Lexer::lex("=", None);
 
-        assert_eq!(lexer.output(), vec![Symbol(0, Equals)]);
+        assert_eq!(lexer.output(), vec![symbol_at(0, Equals)]);
     }
     #[test]
     fn forward_slash_operator() {
-        let lexer = Lexer::new("/");
+        let lexer = Lexer::lex("/", None);
 
-        assert_eq!(lexer.output(), vec![Symbol(0, ForwardSlash)]);
+        assert_eq!(lexer.output(), vec![symbol_at(0, ForwardSlash)]);
     }
     #[test]
     fn open_brace_operator() {
-        let lexer = Lexer::new("{");
+        let lexer = Lexer::lex("{", None);
 
-        assert_eq!(lexer.output(), vec![Symbol(0, OpenBrace)]);
+        assert_eq!(lexer.output(), vec![symbol_at(0, OpenBrace)]);
     }
     #[test]
     fn open_param_operator() {
-        let lexer = Lexer::new("(");
+        let lexer = Lexer::lex("(", None);
 
-        assert_eq!(lexer.output(), vec![Symbol(0, OpenParam)]);
+        assert_eq!(lexer.output(), vec![symbol_at(0, OpenParam)]);
     }
     #[test]
     fn pound_operator() {
-        let lexer = Lexer::new("#");
+        let lexer = Lexer::lex("#", None);
 
-        assert_eq!(lexer.output(), vec![Symbol(0, Pound)]);
+        assert_eq!(lexer.output(), vec![symbol_at(0, Pound)]);
     }
     #[test]
     fn quote_operator() {
-        let lexer = Lexer::new("\"");
+        let lexer = Lexer::lex("\"", None);
+
+        assert_eq!(lexer.output(), vec![symbol_at(0, Quote)]);
+    }
+    #[test]
+    fn string_literals() {
+        let output = Lexer::lex(r#"/a(title="Say \"hi\"\n" alt='It\'s' x="\u{1F600}\q")"#, None)
+                         .output();
+        let strings: Vec<&str> = output.iter()
+                                       .filter_map(|lexeme| {
+                                           match *lexeme {
+                                               Str(_, ref string, _) => Some(&**string),
+                                               _ => None,
+                                           }
+                                       })
+                                       .collect();
+
+        assert_eq!(strings, vec!["Say \"hi\"\n", "It's", "\u{1F600}\\q"]);
+        assert_eq!(output[5],
+                   Str(Span::new(None, 9, 23, 1, 10),
+                       String::from("Say \"hi\"\n"),
+                       String::from(r#""Say \"hi\"\n""#)));
+    }
 
-        assert_eq!(lexer.output(), vec![Symbol(0, Quote)]);
+    #[test]
+    fn unterminated_string_literal() {
+        let output = Lexer::lex(r#"/p(title="abc) {Hello}"#, None).output();
+
+        assert_eq!(output[5],
+                   Str(Span::new(None, 9, 10, 1, 10), String::new(), String::from("\"")));
+        assert!(output[5].is_unterminated_string());
+        assert_eq!(output[7], symbol_at(13, CloseParam));
     }
+
+    #[test]
+    fn quotes_in_text_arent_strings() {
+        let lexer = Lexer::lex(r#"/p {"Don't" (he said)}"#, None);
+
+        assert_eq!(lexer.output()[3], symbol_at(4, Quote));
+    }
+
     #[test]
     fn star_operator() {
-        let lexer = Lexer::new("*");
+        let lexer = Lexer::lex("*", None);
 
-        assert_eq!(lexer.output(), vec![Symbol(0, Star)]);
+        assert_eq!(lexer.output(), vec![symbol_at(0, Star)]);
     }
     #[test]
     fn all_operators() {
-        let lexer = Lexer::new("&@\\})$.=/{(#\"*,");
-        let expected = vec![Symbol(0, Ampersand),
-                            Symbol(1, At),
-                            Symbol(2, BackSlash),
-                            Symbol(3, CloseBrace),
-                            Symbol(4, CloseParam),
-                            Symbol(5, Dollar),
-                            Symbol(6, Dot),
-                            Symbol(7, Equals),
-                            Symbol(8, ForwardSlash),
-                            Symbol(9, OpenBrace),
-                            Symbol(10, OpenParam),
-                            Symbol(11, Pound),
-                            Symbol(12, Quote),
-                            Symbol(13, Star),
-                            Symbol(14, Comma)];
+        let lexer = Lexer::lex("&@\\})$.=/{(#\"*,", None);
+        let expected = vec![symbol_at(0, Ampersand),
+                            symbol_at(1, At),
+                            symbol_at(2, BackSlash),
+                            symbol_at(3, CloseBrace),
+                            symbol_at(4, CloseParam),
+                            symbol_at(5, Dollar),
+                            symbol_at(6, Dot),
+                            symbol_at(7, Equals),
+                            symbol_at(8, ForwardSlash),
+                            symbol_at(9, OpenBrace),
+                            symbol_at(10, OpenParam),
+                            symbol_at(11, Pound),
+                            symbol_at(12, Quote),
+                            symbol_at(13, Star),
+                            symbol_at(14, Comma)];
 
         for (actual, expected) in lexer.output().iter().zip(expected.iter()) {
             assert_eq!(actual, expected);
@@ -237,56 +541,106 @@ mod tests {
     }
     #[test]
     fn word() {
-        let lexer = Lexer::new("Hello");
+        let lexer = Lexer::lex("Hello", None);
 
-        assert_eq!(lexer.output(), vec![Word(0, "Hello".to_owned())]);
+        assert_eq!(lexer.output(), vec![word_at(0, "Hello")]);
     }
     #[test]
     fn words() {
-        let lexer = Lexer::new("The Lord Of The Rings");
+        let lexer = Lexer::lex("The Lord Of The Rings", None);
 
         assert_eq!(lexer.output(),
-                   vec![Word(0, "The ".to_owned()),
-                        Word(4, "Lord ".to_owned()),
-                        Word(9, "Of ".to_owned()),
-                        Word(12, "The ".to_owned()),
-                        Word(16, "Rings".to_owned())]);
+                   vec![word_at(0, "The "),
+                        word_at(4, "Lord "),
+                        word_at(9, "Of "),
+                        word_at(12, "The "),
+                        word_at(16, "Rings")]);
     }
     #[test]
     fn words_and_operators() {
-        let lexer = Lexer::new("@{Hello}.");
+        let lexer = Lexer::lex("@{Hello}.", None);
+
+        assert_eq!(lexer.output(),
+                   vec![symbol_at(0, At),
+                        symbol_at(1, OpenBrace),
+                        word_at(2, "Hello"),
+                        symbol_at(7, CloseBrace),
+                        symbol_at(8, Dot)]);
+    }
+    #[test]
+    fn spans_across_lines() {
+        let output = Lexer::with_file("/p {\n    Hello\n}", "index.polly").output();
+        let hello = output[3].span();
+        let close_brace = output[4].span();
+
+        assert_eq!(hello.file.as_ref().unwrap().to_str(), Some("index.polly"));
+        assert_eq!((hello.start, hello.end, hello.line, hello.col), (9, 15, 2, 5));
+        assert_eq!((close_brace.start, close_brace.end, close_brace.line, close_brace.col),
+                   (15, 16, 3, 1));
+    }
+    #[test]
+    fn block_comment() {
+        let lexer = Lexer::lex("/* A comment\n over lines */", None);
+        let span = Span::new(None, 0, 27, 1, 1);
 
         assert_eq!(lexer.output(),
-                   vec![Symbol(0, At),
-                        Symbol(1, OpenBrace),
-                        Word(2, "Hello".to_string()),
-                        Symbol(7, CloseBrace),
-                        Symbol(8, Dot)]);
+                   vec![Comment(span, String::from("A comment\n over lines"))]);
     }
+
+    #[test]
+    fn line_comment() {
+        let output = Lexer::lex("Hello // A comment\n/p", None).output();
+
+        assert_eq!(output[1],
+                   Comment(Span::new(None, 6, 18, 1, 7), String::from("A comment")));
+        assert_eq!(output[2], Symbol(Span::new(None, 19, 20, 2, 1), ForwardSlash));
+    }
+
+    #[test]
+    fn slashes_after_words_arent_comments() {
+        let lexer = Lexer::lex("image/*", None);
+
+        assert_eq!(lexer.output(),
+                   vec![word_at(0, "image"), symbol_at(5, ForwardSlash), symbol_at(6, Star)]);
+    }
+
     #[test]
     fn hello_world() {
-        let lexer = Lexer::new("/html{ /body { /p{Hello /u{World}!}}}");
-        let expected_tokens = vec![Symbol(0, ForwardSlash),
-                                   Word(1, "html".to_owned()),
-                                   Symbol(5, OpenBrace),
-                                   Symbol(7, ForwardSlash),
-                                   Word(8, "body ".to_owned()),
-                                   Symbol(13, OpenBrace),
-                                   Symbol(15, ForwardSlash),
-                                   Word(16, "p".to_owned()),
-                                   Symbol(17, OpenBrace),
-                                   Word(18, "Hello ".to_owned()),
-                                   Symbol(24, ForwardSlash),
-                                   Word(25, "u".to_owned()),
-                                   Symbol(26, OpenBrace),
-                                   Word(27, "World".to_owned()),
-                                   Symbol(32, CloseBrace),
-                                   Word(33, "!".to_owned()),
-                                   Symbol(34, CloseBrace),
-                                   Symbol(35, CloseBrace),
-                                   Symbol(36, CloseBrace)];
+        let lexer = Lexer::lex("/html{ /body { /p{Hello /u{World}!}}}", None);
+        let expected_tokens = vec![symbol_at(0, ForwardSlash),
+                                   word_at(1, "html"),
+                                   symbol_at(5, OpenBrace),
+                                   symbol_at(7, ForwardSlash),
+                                   word_at(8, "body "),
+                                   symbol_at(13, OpenBrace),
+                                   symbol_at(15, ForwardSlash),
+                                   word_at(16, "p"),
+                                   symbol_at(17, OpenBrace),
+                                   word_at(18, "Hello "),
+                                   symbol_at(24, ForwardSlash),
+                                   word_at(25, "u"),
+                                   symbol_at(26, OpenBrace),
+                                   word_at(27, "World"),
+                                   symbol_at(32, CloseBrace),
+                                   word_at(33, "!"),
+                                   symbol_at(34, CloseBrace),
+                                   symbol_at(35, CloseBrace),
+                                   symbol_at(36, CloseBrace)];
         for (actual, expected) in lexer.output().iter().zip(expected_tokens.iter()) {
             assert_eq!(actual, expected);
         }
     }
+
+    #[test]
+    fn variable_indexes() {
+        let output = Lexer::lex(r#"@items[0]["a.b"] [1]"#, None).output();
+
+        assert_eq!(output[2], symbol_at(6, OpenBracket));
+        assert_eq!(output[4], symbol_at(8, CloseBracket));
+        assert_eq!(output[6],
+                   Str(Span::new(None, 10, 15, 1, 11),
+                       String::from("a.b"),
+                       String::from(r#""a.b""#)));
+        assert_eq!(output[8], word_at(17, " [1"));
+    }
 }
@@ -1,9 +1,11 @@
 pub mod lexer;
 pub mod parser;
 pub mod codegen;
+pub mod escape;
 pub mod tokens;
 
 pub use self::lexer::*;
 pub use self::parser::*;
 pub use self::codegen::*;
+pub use self::escape::*;
 pub use self::tokens::*;
@@ -2,9 +2,14 @@ use std::collections::HashMap;
 use std::iter::Peekable;
 use std::vec::IntoIter;
 
+use serde_json;
+use serde_json::Value;
+
 use super::tokens::*;
+use super::tokens::Variable;
 use super::tokens::AstError::*;
 use super::tokens::Lexeme::*;
+use super::tokens::Lexeme::Comment;
 use super::tokens::Operator::*;
 use super::tokens::Token::*;
 
@@ -20,7 +25,7 @@ macro_rules! unexpected_eof {
 macro_rules! get_identifer {
     ($token:expr, $index:expr, $unexpected:expr) => {
         match $token {
-            Some(Word(_, text)) => text,
+            Some(Word(span, text)) => (text, span),
             Some(unexpected_token) => {
                 return Err($unexpected(unexpected_token))
             }
@@ -29,22 +34,27 @@ macro_rules! get_identifer {
     }
 }
 
+/// Gets an identifier made of words separated by dots, returning it along with its span.
 macro_rules! get_namespaced_identifer {
     ($this:expr, $index:expr, $unexpected:expr, $previous:expr) => {
         match $this.take() {
             Some(Word(index, text)) => {
                 let mut new_text = text.clone();
+                let mut span = index.clone();
                 while let Some(Symbol(_, Dot)) = $this.peek() {
                     let _ = $this.take();
                     new_text.push('.');
 
                     match $this.take() {
-                        Some(Word(_, member)) => new_text.push_str(&*member),
+                        Some(Word(member_span, member)) => {
+                            new_text.push_str(&*member);
+                            span = span.to(&member_span);
+                        }
                         Some(unexpected_token) => return Err($unexpected(unexpected_token)),
-                        None => return Err(UnexpectedEof(Symbol(index, Dot))),
+                        None => return Err(UnexpectedEof(Symbol(index.clone(), Dot))),
                     }
                 }
-                new_text
+                (new_text, span)
             }
             Some(unexpected_token) => return Err($unexpected(unexpected_token)),
             None => return Err(UnexpectedEof(Symbol($index, $previous))),
@@ -52,44 +62,44 @@ macro_rules! get_namespaced_identifer {
     }
 }
 
+/// Parses everything up to the brace closing `$open_brace` as the children of `$parent`. The
+/// children are parsed even if the brace is never closed, so their errors are reported as well.
 macro_rules! get_children {
-    ($token:expr, $parent:expr) => 
+    ($token:expr, $parent:expr, $open_brace:expr) => 
     {{
         let mut depth: usize = 0;
-        let mut open_brace_index: usize = 0;
-        let mut close_brace_index: usize = 0;
+        let mut closed = false;
         let mut children = Vec::new();
         while let Some(token) = $token {
             match token {
                 Symbol(index, OpenBrace) => {
                     depth += 1;
-
-                    if depth != 0 {
-                        children.push(Symbol(index, OpenBrace));
-                    }
-                    open_brace_index = index;
+                    children.push(Symbol(index, OpenBrace));
                 }
                 Symbol(index, CloseBrace) => {
                     if depth == 0 {
+                        closed = true;
                         break;
-                    } else {
-                        depth -= 1;
-                        children.push(Symbol(index, CloseBrace));
                     }
-                    close_brace_index = index;
+                    depth -= 1;
+                    children.push(Symbol(index, CloseBrace));
+                }
+                // An escaped brace doesn't open, or close anything.
+                Symbol(index, BackSlash) => {
+                    children.push(Symbol(index, BackSlash));
+                    if let Some(escaped) = $token {
+                        children.push(escaped);
+                    }
                 }
                 t => children.push(t),
             }
         }
 
-        if depth > 0 {
-            return Err(UnclosedOpenBraces(open_brace_index));
-        } else if depth != 0 {
-            return Err(UnclosedCloseBraces(close_brace_index));
-        }
-        if !children.is_empty() {
-            $parent.add_children(&mut Parser::new(children).output());
+        let mut children = Parser::nested(children).output();
+        if !closed {
+            children.push(Err(UnclosedOpenBraces($open_brace)));
         }
+        $parent.add_children(&mut children);
     }}
 }
 
@@ -99,43 +109,127 @@ pub struct Parser {
     input: Peekable<IntoIter<Lexeme>>,
     output: Vec<AstResult>,
     components: HashMap<String, Component>,
+    /// Whether the parser is parsing the children of an element or component, where components
+    /// can't be defined.
+    nested: bool,
 }
 
 impl Parser {
     /// Generates Parser from Lexer
     pub fn new(lexemes: Vec<Lexeme>) -> Self {
+        Parser::parse(Parser::new_parser(lexemes))
+    }
+
+    /// Parses the children of an element or component. Components can only be defined at the
+    /// top level of a file, so here `&name(..) { .. }` is a call, with the braces holding the
+    /// children passed to the component.
+    fn nested(lexemes: Vec<Lexeme>) -> Self {
         let mut parser = Parser::new_parser(lexemes);
+        parser.nested = true;
+        Parser::parse(parser)
+    }
+
+    fn parse(mut parser: Parser) -> Self {
         loop {
             match parser.parse_token() {
                 Err(Eof) => break,
+                // A stray brace on its own doesn't affect anything around it.
+                error @ Err(UnclosedCloseBraces(_)) => parser.push(error),
+                Err(error) => {
+                    parser.recover(&error);
+                    parser.push(Err(error));
+                }
                 token => parser.push(token),
             }
         }
         parser
     }
 
+    /// Skips the rest of a construct that failed to parse, so that the errors after it are still
+    /// reported, without it causing errors of its own. Everything left on the line the error was
+    /// on is skipped, along with any block of children opened on it.
+    fn recover(&mut self, error: &AstError) {
+        let line = error.span().map(|span| span.line);
+        // The brace opening the block may have been the token that caused the error.
+        let mut depth: usize = match error.lexeme() {
+            Some(&Symbol(_, OpenBrace)) => 1,
+            _ => 0,
+        };
+        while let Some(token) = self.peek() {
+            match token {
+                Symbol(_, OpenBrace) => depth += 1,
+                Symbol(_, CloseBrace) => {
+                    if depth == 0 {
+                        break;
+                    }
+                    depth -= 1;
+                    if depth == 0 {
+                        let _ = self.take();
+                        break;
+                    }
+                }
+                ref token if depth == 0 && Some(token.span().line) != line => break,
+                _ => {}
+            }
+            let _ = self.take();
+        }
+    }
+
     fn new_parser(lexemes: Vec<Lexeme>) -> Self {
+        let output = Parser::unterminated_strings(&lexemes);
         Parser {
             input: lexemes.into_iter().peekable(),
-            output: Vec::new(),
+            output: output,
             components: HashMap::new(),
+            nested: false,
         }
     }
 
+    /// Finds the string literals that are never closed, outside of any braces. The ones inside
+    /// braces are found by the parser of those children, so each is only reported once.
+    fn unterminated_strings(lexemes: &[Lexeme]) -> Vec<AstResult> {
+        let mut errors = Vec::new();
+        let mut depth: usize = 0;
+        let mut lexemes = lexemes.iter();
+        while let Some(lexeme) = lexemes.next() {
+            match *lexeme {
+                Symbol(_, BackSlash) => {
+                    let _ = lexemes.next();
+                }
+                Symbol(_, OpenBrace) => depth += 1,
+                Symbol(_, CloseBrace) => depth = depth.saturating_sub(1),
+                ref lexeme if depth == 0 && lexeme.is_unterminated_string() => {
+                    errors.push(Err(UnterminatedString(lexeme.span().clone())))
+                }
+                _ => {}
+            }
+        }
+        errors
+    }
+
     /// Pushes a new AstResult onto the output Vector
     fn push(&mut self, token: AstResult) {
         self.output.push(token);
     }
 
-    /// A wrapper function around the input. taking the next element from the iterator.
+    /// A wrapper function around the input. taking the next element from the iterator. Comments
+    /// are skipped, as they can be placed anywhere, such as in between an element's attributes.
     fn take(&mut self) -> Option<Lexeme> {
-        self.input.next()
+        loop {
+            match self.input.next() {
+                Some(Comment(..)) => {}
+                lexeme => return lexeme,
+            }
+        }
     }
     /// Performs a lookahead of the iterator.
     // This function should probably be refactored to not clone a token every time it's called.
     // Currently if you replace it with a reference, it creates a borrow, that messes up the
     // parser's current borrow structure.
     fn peek(&mut self) -> Option<Lexeme> {
+        while let Some(&Comment(..)) = self.input.peek() {
+            let _ = self.input.next();
+        }
         match self.input.peek() {
             Some(token) => Some(token.clone()),
             None => None,
@@ -150,14 +244,41 @@ impl Parser {
         self.components.clone()
     }
 
-    /// Only parse components out of the source.
+    /// Collects every error in the AST, including the ones in the children of elements.
+    pub fn errors(ast: &[AstResult]) -> Vec<AstError> {
+        let mut errors = Vec::new();
+        for token in ast {
+            match *token {
+                Ok(Html(ref element)) => errors.extend(Parser::errors(element.children())),
+                Ok(CompCall(ref component_call)) => {
+                    errors.extend(Parser::errors(component_call.children()))
+                }
+                Ok(_) => {}
+                Err(ref error) => errors.push(error.clone()),
+            }
+        }
+        errors
+    }
+
+    /// Only parse components out of the source. Only the top level of the source is searched, as
+    /// components inside of braces are calls.
     pub fn component_pass(lexemes: Vec<Lexeme>) -> HashMap<String, Component> {
         let mut parser = Parser::new_parser(lexemes);
+        let mut depth: usize = 0;
         loop {
             match parser.take() {
                 Some(Symbol(index, Ampersand)) => {
-                    let _ = parser.parse_component(true, index);
+                    if depth == 0 {
+                        let _ = parser.parse_component(true, index);
+                    }
                 }
+                Some(Symbol(_, BackSlash)) => {
+                    if let Some(Symbol(_, _)) = parser.peek() {
+                        let _ = parser.take();
+                    }
+                }
+                Some(Symbol(_, OpenBrace)) => depth += 1,
+                Some(Symbol(_, CloseBrace)) => depth = depth.saturating_sub(1),
                 None => break,
                 _ => {}
             }
@@ -166,9 +287,15 @@ impl Parser {
         parser.components
     }
 
-    fn parse_component(&mut self, allow_definition: bool, index: usize) -> AstResult {
-        let name = get_namespaced_identifer!(self, index, InvalidComponent, Ampersand);
-        let mut component = Component::new(name);
+    fn parse_component(&mut self, allow_definition: bool, index: Span) -> AstResult {
+        let (name, span) = get_namespaced_identifer!(self, index, InvalidComponent, Ampersand);
+        let mut component = Component::new(name, span);
+        // Named arguments, literals, and paths into variables are only allowed in calls, and
+        // default values only in definitions, but which one this is isn't known until after the
+        // parentheses.
+        let mut named = Vec::new();
+        let mut first_call_only = None;
+        let mut first_default = None;
 
         while let Some(token) = self.peek() {
             match token {
@@ -177,17 +304,68 @@ impl Parser {
                     while let Some(token) = self.take() {
                         match token {
                             Symbol(index, At) => {
+                                let (variable, _) = match self.read_variable(index) {
+                                    Ok(variable) => variable,
+                                    Err(error) => return Err(error),
+                                };
 
-                                let identifier = get_identifer!(self.take(),
-                                                                index,
-                                                                UnexpectedToken);
-                                component.add_arg_value(identifier);
+                                if let Some(Symbol(equals, Equals)) = self.peek() {
+                                    let _ = self.take();
+                                    let default = match self.read_default(equals.clone()) {
+                                        Ok(default) => default,
+                                        Err(error) => return Err(error),
+                                    };
+                                    component.add_arg_default(variable.name(), default);
+                                    if first_default.is_none() {
+                                        first_default = Some(Symbol(equals, Equals));
+                                    }
+                                } else {
+                                    if variable.name().contains('.') && first_call_only.is_none() {
+                                        first_call_only = Some(Word(variable.span().clone(),
+                                                                    variable.name().to_owned()));
+                                    }
+                                    component.add_arg_value(variable.name());
+                                }
+                            }
+                            Symbol(index, Ampersand) => {
+                                let (identifier, _) = get_namespaced_identifer!(self,
+                                                                                index,
+                                                                                UnexpectedToken,
+                                                                                Ampersand);
+                                component.add_arg_component(identifier);
+                            }
+                            Word(index, key) => {
+                                if first_call_only.is_none() {
+                                    first_call_only = Some(Word(index.clone(), key.clone()));
+                                }
+
+                                if let Some(Symbol(equals, Equals)) = self.peek() {
+                                    let _ = self.take();
+                                    match self.read_named_argument(equals) {
+                                        Ok(value) => named.push((key, value)),
+                                        Err(error) => return Err(error),
+                                    }
+                                } else {
+                                    match self.read_literal(index, key) {
+                                        Ok(value) => component.add_arg_literal(value),
+                                        Err(error) => return Err(error),
+                                    }
+                                }
+                            }
+                            Str(index, string, source) => {
+                                if first_call_only.is_none() {
+                                    first_call_only = Some(Str(index, string.clone(), source));
+                                }
+                                component.add_arg_literal(Value::String(string));
                             }
                             Symbol(_, CloseParam) => {
                                 match self.peek() {
                                     Some(Symbol(_, OpenBrace)) => break,
                                     _ => {
-                                        return Ok(CompCall((ComponentCall::from_component(component))));
+                                        return Parser::component_call(component,
+                                                                      named,
+                                                                      first_default)
+                                                   .map(CompCall);
                                     }
                                 }
                             }
@@ -196,13 +374,23 @@ impl Parser {
                         }
                     }
                 }
-                token @ Symbol(_, OpenBrace) => {
+                Symbol(index, OpenBrace) => {
                     let _ = self.take();
                     if allow_definition {
-                        get_children!(self.take(), component);
+                        if let Some(lexeme) = first_call_only {
+                            return Err(UnexpectedToken(lexeme));
+                        }
+                        get_children!(self.input.next(), component, index);
                         break;
                     } else {
-                        return Err(ExpectedCompCall(token));
+                        let mut component_call = match Parser::component_call(component,
+                                                                              named,
+                                                                              first_default) {
+                            Ok(component_call) => component_call,
+                            Err(error) => return Err(error),
+                        };
+                        get_children!(self.input.next(), component_call, index);
+                        return Ok(CompCall(component_call));
                     }
                 }
                 unexpected_token => return Err(UnexpectedToken(unexpected_token)),
@@ -212,38 +400,104 @@ impl Parser {
             self.components.insert(component.name().into(), component);
             Ok(Text(String::new()))
         } else {
-            // This unreachable, because with allow_definition = false, we should either get a
-            // CompCall, or a ExpectedCompCall error.
-            unreachable!()
+            Err(UnexpectedEof(Symbol(index, Ampersand)))
         }
     }
 
-    fn parse_element(&mut self, index: usize) -> AstResult {
-        let tag = get_identifer!(self.take(), index, InvalidElement);
-        let mut element = Element::new(tag.trim().to_owned());
+    /// Turns a component that turned out to be a call into a `ComponentCall`, with the named
+    /// arguments `named`. `first_default` is the first default value given, which can only be
+    /// given when defining a component.
+    fn component_call(component: Component,
+                      named: Vec<(String, ArgKey)>,
+                      first_default: Option<Lexeme>)
+                      -> Result<ComponentCall, AstError> {
+        if let Some(equals) = first_default {
+            return Err(UnexpectedToken(equals));
+        }
+
+        let mut component_call = ComponentCall::from_component(component);
+        for (key, value) in named {
+            component_call.add_named(key, value);
+        }
+        Ok(component_call)
+    }
+
+    /// Reads an argument starting with `token`, which is either a path into a variable, a
+    /// component, or a literal. Every kind of call reads its arguments with this, so they all
+    /// accept the same arguments.
+    fn read_argument(&mut self, token: Lexeme) -> Result<ArgKey, AstError> {
+        match token {
+            Symbol(index, At) => {
+                match self.read_variable(index) {
+                    Ok((variable, _)) => Ok(ArgKey::Json(variable.name().to_owned())),
+                    Err(error) => Err(error),
+                }
+            }
+            Symbol(index, Ampersand) => {
+                let (name, _) = get_namespaced_identifer!(self, index, ExpectedCompCall, Ampersand);
+                Ok(ArgKey::Comp(name.trim().to_owned()))
+            }
+            Str(_, string, _) => Ok(ArgKey::Literal(Value::String(string))),
+            Word(index, text) => self.read_literal(index, text).map(ArgKey::Literal),
+            unexpected_token => Err(UnexpectedToken(unexpected_token)),
+        }
+    }
+
+    /// Reads the value of a named argument following its `=`.
+    fn read_named_argument(&mut self, equals: Span) -> Result<ArgKey, AstError> {
+        match self.take() {
+            Some(token) => self.read_argument(token),
+            None => Err(UnexpectedEof(Symbol(equals, Equals))),
+        }
+    }
+
+    /// Reads the default value of a component's parameter following its `=`, which has to be a
+    /// literal.
+    fn read_default(&mut self, equals: Span) -> Result<Value, AstError> {
+        match self.take() {
+            Some(Str(_, string, _)) => Ok(Value::String(string)),
+            Some(Word(index, text)) => self.read_literal(index, text),
+            Some(unexpected_token) => Err(InvalidLiteral(unexpected_token)),
+            None => Err(UnexpectedEof(Symbol(equals, Equals))),
+        }
+    }
+
+    fn parse_element(&mut self, index: Span) -> AstResult {
+        let (tag, span) = get_identifer!(self.take(), index, InvalidElement);
+        let mut element = Element::new(tag.trim().to_owned(), span);
 
         'element: while let Some(token) = self.take() {
             match token {
                 Symbol(index, Ampersand) => {
-                    let identifier = get_namespaced_identifer!(self,
-                                                               index,
-                                                               ExpectedCompCall,
-                                                               Ampersand);
-                    let mut component_call = ComponentCall::new(identifier);
+                    let (identifier, span) = get_namespaced_identifer!(self,
+                                                                       index,
+                                                                       ExpectedCompCall,
+                                                                       Ampersand);
+                    let mut component_call = ComponentCall::new(identifier, span);
 
                     if let Some(Symbol(_, OpenParam)) = self.peek() {
                         let _ = self.take();
                         while let Some(symbol) = self.take() {
-                            match symbol {
+                            let argument = match symbol {
                                 Symbol(_, CloseParam) => break,
-                                Symbol(index, At) => {
-                                    let identifier = get_identifer!(self.take(),
-                                                                    index,
-                                                                    ExpectedVariable);
-                                    component_call.add_value(identifier);
+                                Symbol(_, Comma) => continue,
+                                Word(index, key) => {
+                                    if let Some(Symbol(equals, Equals)) = self.peek() {
+                                        let _ = self.take();
+                                        match self.read_named_argument(equals) {
+                                            Ok(value) => component_call.add_named(key, value),
+                                            Err(error) => return Err(error),
+                                        }
+                                        continue;
+                                    }
+                                    self.read_argument(Word(index, key))
                                 }
-                                Symbol(_, Comma) => {}
-                                unexpected_token => return Err(UnexpectedToken(unexpected_token)),
+                                token => self.read_argument(token),
+                            };
+
+                            match argument {
+                                Ok(value) => component_call.add_argument(value),
+                                Err(error) => return Err(error),
                             }
                         }
                     }
@@ -258,32 +512,44 @@ impl Parser {
                                     _ => return Ok(Html(element)),
                                 }
                             }
-                            Symbol(_, Quote) => {
-                                let key = format!("{}{}{}", '"', self.read_leading_quotes(), '"');
-                                element.add_attribute(key, String::from(""));
+                            Str(_, key, _) => {
+                                element.add_attribute(format!("\"{}\"", key), String::from(""));
                             }
                             Word(_, key) => {
                                 let value = match self.peek() {
                                     Some(Symbol(index, Equals)) => {
                                         let _ = self.take();
                                         match self.take() {
-                                            Some(Word(_, text)) => text,
-                                            Some(Symbol(_, Quote)) => self.read_leading_quotes(),
+                                            Some(Word(_, text)) | Some(Str(_, text, _)) => text,
+                                            Some(Symbol(index, At)) => {
+                                                let (mut variable, whitespace) =
+                                                    match self.read_variable(index) {
+                                                        Ok(variable) => variable,
+                                                        Err(error) => return Err(error),
+                                                    };
+                                                if let Err(error) =
+                                                       self.read_filters(&mut variable,
+                                                                         whitespace) {
+                                                    return Err(error);
+                                                }
+                                                element.add_variable_attribute(key, variable);
+                                                continue;
+                                            }
                                             Some(unexpected_token) => {
                                                 return Err(InvalidTokenInAttributes(unexpected_token));
                                             }
                                             None => {
-                                                return unexpected_eof!(Symbol(index, Equals));
+                                                unexpected_eof!(Symbol(index, Equals));
                                             }
                                         }
                                     }
                                     Some(Word(_, _)) => String::from(""),
                                     Some(Symbol(_, CloseParam)) => String::from(""),
-                                    Some(Symbol(_, Quote)) => String::from(""),
+                                    Some(Str(..)) => String::from(""),
                                     Some(invalid_token) => {
                                         return Err(InvalidTokenInAttributes(invalid_token))
                                     }
-                                    None => return unexpected_eof!(Word(index, key)),
+                                    None => unexpected_eof!(Word(index.clone(), key)),
                                 };
 
                                 element.add_attribute(key, value);
@@ -308,8 +574,8 @@ impl Parser {
                         None => return Err(UnexpectedEof(Symbol(index, Pound))),
                     }
                 }
-                Symbol(_, OpenBrace) => {
-                    get_children!(self.take(), element);
+                Symbol(index, OpenBrace) => {
+                    get_children!(self.input.next(), element, index);
                     break;
                 }
                 unexpected_token => return Err(UnexpectedToken(unexpected_token)),
@@ -320,18 +586,21 @@ impl Parser {
 
     fn parse_escaped(&mut self) -> AstResult {
         match self.peek() {
-            Some(Symbol(_, ref operator)) => {
+            Some(Symbol(span, ref operator)) => {
                 let _ = self.take();
-                Ok(Text(operator.to_string()))
+                Ok(Text(format!("{}{}", operator, self.space_after(&span))))
             }
             Some(_) => Ok(Text(String::new())),
             None => Err(Eof),
         }
     }
 
-    fn parse_function(&mut self, index: usize) -> AstResult {
-        let identifier = get_namespaced_identifer!(self, index, InvalidFunctionCall, Dollar);
-        let mut func_call = FunctionCall::new(identifier);
+    fn parse_function(&mut self, index: Span) -> AstResult {
+        let (identifier, span) = get_namespaced_identifer!(self,
+                                                           index.clone(),
+                                                           InvalidFunctionCall,
+                                                           Dollar);
+        let mut func_call = FunctionCall::new(identifier, span);
 
         match self.take() {
             Some(Symbol(_, OpenParam)) => {
@@ -339,43 +608,16 @@ impl Parser {
                     match token {
                         Word(index, arg_name) => {
                             match self.take() {
-                                Some(Symbol(index, Equals)) => {
-                                    match self.take() {
-                                        Some(Symbol(index, At)) => {
-                                            match self.take() {
-                                                Some(Word(_, identifier)) => {
-                                                    func_call.add_value_arg(arg_name, identifier);
-                                                }
-                                                Some(unexpected_token) => {
-                                                    return Err(ExpectedVariable(unexpected_token))
-                                                }
-                                                None => unexpected_eof!(Symbol(index, At)),
-                                            }
-                                        }
-                                        Some(Symbol(index, Ampersand)) => {
-                                            match self.take() {
-                                                Some(Word(_, identifier)) => {
-                                                    func_call.add_component_arg(arg_name,
-                                                                                identifier);
-                                                }
-                                                Some(unexpected_token) => {
-                                                    return Err(ExpectedCompCall(unexpected_token))
-                                                }
-                                                None => unexpected_eof!(Symbol(index, Ampersand)),
-                                            }
-                                        }
-                                        Some(unexpected_token) => {
-                                            return Err(UnexpectedToken(unexpected_token))
-                                        }
-                                        None => unexpected_eof!(Symbol(index, Equals)),
-
+                                Some(Symbol(equals, Equals)) => {
+                                    match self.read_named_argument(equals) {
+                                        Ok(value) => func_call.add_arg(arg_name, value),
+                                        Err(error) => return Err(error),
                                     }
                                 }
                                 Some(unexpected_token) => {
                                     return Err(InvalidFunctionCall(unexpected_token))
                                 }
                                 None => unexpected_eof!(Word(index, arg_name)),
-
                             }
                         }
                         Symbol(_, CloseParam) => break,
@@ -392,14 +634,183 @@ impl Parser {
 
 
 
+    /// Reads a number, boolean, or null literal starting with the word `text`. The lexer splits
+    /// numbers with a fraction at the ".", so they're put back together here.
+    fn read_literal(&mut self, index: Span, text: String) -> Result<Value, AstError> {
+        let mut literal = text.trim().to_owned();
+
+        if !text.ends_with(char::is_whitespace) {
+            if let Some(Symbol(dot, Dot)) = self.peek() {
+                let _ = self.take();
+                match self.take() {
+                    Some(Word(_, ref fraction)) => {
+                        literal.push('.');
+                        literal.push_str(fraction.trim());
+                    }
+                    Some(unexpected_token) => return Err(InvalidLiteral(unexpected_token)),
+                    None => unexpected_eof!(Symbol(dot, Dot)),
+                }
+            }
+        }
+
+        match serde_json::from_str(&literal) {
+            Ok(value) => Ok(value),
+            Err(_) => Err(InvalidLiteral(Word(index, text))),
+        }
+    }
+
+    fn parse_variable(&mut self, index: Span) -> AstResult {
+        let (mut variable, whitespace) = match self.read_variable(index) {
+            Ok(variable) => variable,
+            Err(error) => return Err(error),
+        };
+        let whitespace = match self.read_filters(&mut variable, whitespace) {
+            Ok(whitespace) => whitespace,
+            Err(error) => return Err(error),
+        };
+
+        // The lexer attaches trailing whitespace to the last word of the variable, so it's
+        // pushed back out as text, otherwise "@name text" would be rendered as "nametext".
+        if whitespace.is_empty() {
+            Ok(Variable(variable))
+        } else {
+            self.push(Ok(Variable(variable)));
+            Ok(Text(whitespace))
+        }
+    }
+
+    /// Reads the variable following an @, returning it along with any whitespace that was
+    /// attached to the end of it. The name of the variable is its whole path, such as
+    /// `items[0].name`, with the keys in brackets written as JSON strings.
+    fn read_variable(&mut self, index: Span) -> Result<(Variable, String), AstError> {
+        let (identifier, span) = get_namespaced_identifer!(self,
+                                                           index.clone(),
+                                                           ExpectedVariable,
+                                                           At);
+        let mut name = identifier.trim_right().to_owned();
+        let mut whitespace = identifier[name.len()..].to_owned();
+        let mut span = index.to(&Span { end: span.start + name.len(), ..span });
+
+        while whitespace.is_empty() {
+            let open = match self.peek() {
+                Some(Symbol(open, OpenBracket)) => open,
+                _ => break,
+            };
+            let _ = self.take();
+
+            match self.take() {
+                Some(Str(_, key, _)) => {
+                    name.push('[');
+                    name.push_str(&serde_json::to_string(&key).unwrap_or(key));
+                    name.push(']');
+                }
+                Some(Word(_, ref number)) if number.trim().parse::<i64>().is_ok() => {
+                    name.push_str(&format!("[{}]", number.trim()));
+                }
+                Some(unexpected_token) => return Err(ExpectedVariable(unexpected_token)),
+                None => unexpected_eof!(Symbol(open, OpenBracket)),
+            }
+
+            match self.take() {
+                Some(Symbol(close, CloseBracket)) => span = span.to(&close),
+                Some(unexpected_token) => return Err(UnexpectedToken(unexpected_token)),
+                None => unexpected_eof!(Symbol(open, OpenBracket)),
+            }
+
+            // The path can carry on after the brackets, such as `@items[0].name`.
+            while let Some(Symbol(dot, Dot)) = self.peek() {
+                let _ = self.take();
+                match self.take() {
+                    Some(Word(member_span, member)) => {
+                        let trimmed = member.trim_right().len();
+                        name.push('.');
+                        name.push_str(&member[..trimmed]);
+                        whitespace = member[trimmed..].to_owned();
+                        span = span.to(&Span { end: member_span.start + trimmed, ..member_span });
+                        if !whitespace.is_empty() {
+                            break;
+                        }
+                    }
+                    Some(unexpected_token) => return Err(ExpectedVariable(unexpected_token)),
+                    None => unexpected_eof!(Symbol(dot, Dot)),
+                }
+            }
+        }
+
+        let variable = if name.starts_with(EXCLAMATION) {
+            Variable::raw(&name[1..], span)
+        } else {
+            Variable::new(&*name, span)
+        };
+
+        if variable.name().is_empty() {
+            return Err(ExpectedVariable(Word(index, identifier.clone())));
+        }
+
+        Ok((variable, whitespace))
+    }
+
+    /// Reads the filters following a variable, such as `| truncate(20)`, returning whatever
+    /// whitespace ends up after the last one.
+    fn read_filters(&mut self,
+                    variable: &mut Variable,
+                    whitespace: String)
+                    -> Result<String, AstError> {
+        let mut whitespace = whitespace;
+
+        while let Some(Symbol(pipe, Pipe)) = self.peek() {
+            let _ = self.take();
+            let (name, span) = match self.take() {
+                Some(Word(span, name)) => (name, span),
+                Some(unexpected_token) => return Err(InvalidFilter(unexpected_token)),
+                None => unexpected_eof!(Symbol(pipe, Pipe)),
+            };
+            let mut filter = Filter::new(&*name, span.clone());
+            whitespace = name[name.trim_right().len()..].to_owned();
+
+            if whitespace.is_empty() {
+                if let Some(Symbol(_, OpenParam)) = self.peek() {
+                    let _ = self.take();
+                    loop {
+                        match self.take() {
+                            Some(Symbol(_, CloseParam)) => break,
+                            Some(Symbol(_, Comma)) => {}
+                            Some(token) => {
+                                match self.read_argument(token) {
+                                    Ok(argument) => filter.add_arg(argument),
+                                    Err(error) => return Err(error),
+                                }
+                            }
+                            None => unexpected_eof!(Word(span, name)),
+                        }
+                    }
+                }
+            }
+            variable.add_filter(filter);
+        }
+
+        Ok(whitespace)
+    }
+
+    /// The whitespace to keep after a symbol written as text. Words carry the whitespace before
+    /// them, but a symbol doesn't, so without this `Hello, @name` loses the space after the comma.
+    fn space_after(&mut self, span: &Span) -> &'static str {
+        match self.input.peek() {
+            Some(&Symbol(ref next, _)) |
+            Some(&Str(ref next, _, _)) if next.start > span.end => " ",
+            _ => "",
+        }
+    }
+
     fn parse_text(&mut self, word: String) -> AstResult {
         let mut text = String::from(word);
         loop {
-            let peek = self.peek();
+            // Comments aren't skipped here, so they aren't lost from the text around them.
+            let peek = self.input.peek().cloned();
             match peek {
                 Some(Word(_, ref peek_text)) => {
                     text.push_str(&*peek_text);
-                    let _ = self.take();
+                    let _ = self.input.next();
                 }
                 _ => return Ok(Text(text)),
             }
@@ -408,32 +819,27 @@ impl Parser {
 
     /// 
     fn parse_token(&mut self) -> AstResult {
-        match self.take() {
+        match self.input.next() {
             // concatenate all the word tokens that are adjacent to each other into a single "Text"
             // token.
             Some(Word(_, word)) => self.parse_text(word),
-            Some(Symbol(index, At)) => {
-                Ok(Variable(get_namespaced_identifer!(self, index, ExpectedVariable, At)))
-            }
+            Some(Symbol(index, At)) => self.parse_variable(index),
             Some(Symbol(index, ForwardSlash)) => self.parse_element(index),
             Some(Symbol(_, BackSlash)) => self.parse_escaped(),
-            Some(Symbol(index, Ampersand)) => self.parse_component(true, index),
+            Some(Symbol(index, Ampersand)) => {
+                let allow_definition = !self.nested;
+                self.parse_component(allow_definition, index)
+            }
             Some(Symbol(index, Dollar)) => self.parse_function(index),
-            Some(Symbol(_, operator)) => Ok(Text(operator.to_string())),
-            None => Err(Eof),
-        }
-    }
-
-    /// turns all Operators into text until it it reaches the first " or Quote operator.
-    fn read_leading_quotes(&mut self) -> String {
-        let mut value = String::new();
-        while let Some(token) = self.take() {
-            match token {
-                Symbol(_, Quote) => break,
-                Word(_, text) => value.push_str(&*text),
-                Symbol(_, operator) => value.push_str(&*operator.to_string()),
+            Some(Symbol(index, CloseBrace)) => Err(UnclosedCloseBraces(index)),
+            Some(Comment(_, comment)) => Ok(Token::Comment(comment)),
+            Some(Str(span, _, source)) => {
+                Ok(Text(format!("{}{}", source, self.space_after(&span))))
             }
+            Some(Symbol(span, operator)) => {
+                Ok(Text(format!("{}{}", operator, self.space_after(&span))))
+            }
+            None => Err(Eof),
         }
-        value
     }
 }
@@ -1,9 +1,14 @@
+use std::sync::Arc;
+
+use serde_json;
 use serde_json::Value;
 use super::Component;
 #[derive(Debug, Clone, PartialEq)]
 pub enum ArgKey {
     Json(String),
     Comp(String),
+    /// A string, number, boolean, or null written directly into the template.
+    Literal(Value),
 }
 
 impl ArgKey {
@@ -11,6 +16,12 @@ impl ArgKey {
         use self::ArgKey::*;
         match *self {
             Json(ref string) | Comp(ref string) => string.clone(),
+            Literal(ref value) => {
+                match serde_json::to_string(value) {
+                    Ok(json) => json,
+                    Err(_) => String::new(),
+                }
+            }
         }
     }
 }
@@ -21,5 +32,5 @@ pub enum ArgValue {
     /// JSON passed into the Function, or Component.
     Json(Option<Value>),
     /// Component passed into the Function, or Component.
-    Comp(Option<Component>),
+    Comp(Option<Arc<Component>>),
 }
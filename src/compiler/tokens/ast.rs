@@ -2,7 +2,7 @@ use std::fmt;
 use std::fmt::{Display, Formatter};
 use std::error;
 
-use super::{ComponentCall, Element, FunctionCall, Lexeme};
+use super::{ComponentCall, Element, FunctionCall, Lexeme, Span, Variable};
 use self::AstError::*;
 
 /// TODO
@@ -13,11 +13,13 @@ pub enum Token {
     /// TODO
     Text(String),
     /// TODO
-    Variable(String),
+    Variable(Variable),
     /// TODO
     CompCall(ComponentCall),
     /// TODO
     Function(FunctionCall),
+    /// A comment, which is only rendered when the template has HTML comments turned on.
+    Comment(String),
 }
 
 /// Errors defining all the errors that can be encountered while parsing.
@@ -33,20 +35,26 @@ pub enum AstError {
     InvalidComponent(Lexeme),
     /// No name attached to element.
     InvalidElement(Lexeme),
+    /// No name attached to filter.
+    InvalidFilter(Lexeme),
     /// No name attached to function.
     InvalidFunctionCall(Lexeme),
     /// Token that isn't (, ), =, ", ', or a word. 
     InvalidTokenInAttributes(Lexeme),
+    /// A function argument that isn't a variable, component, string, number, boolean, or null.
+    InvalidLiteral(Lexeme),
     /// Having a . without anything following it up.
     NoNameAttachedToClass(Lexeme),
     /// Having a # without anything following it up.
     NoNameAttachedToId(Lexeme),
     /// Extra } braces
-    UnclosedCloseBraces(usize),
+    UnclosedCloseBraces(Span),
     /// Extra { braces
-    UnclosedOpenBraces(usize),
+    UnclosedOpenBraces(Span),
     /// File ended while we tried to parse element.
     UnexpectedEof(Lexeme),
+    /// A string literal that's never closed. The span covers its opening quote.
+    UnterminatedString(Span),
     /// Unknown token
     UnexpectedToken(Lexeme),
 }
@@ -54,19 +62,38 @@ pub enum AstError {
 impl AstError {
     /// The length of a token, for error printing.
     pub fn values(&self) -> (usize, usize) {
+        match self.span() {
+            Some(span) => (span.start, span.len()),
+            None => (0, 0),
+        }
+    }
+
+    /// Where in the source the error occurred, if anywhere.
+    pub fn span(&self) -> Option<&Span> {
+        match *self {
+            UnclosedCloseBraces(ref span) |
+            UnclosedOpenBraces(ref span) |
+            UnterminatedString(ref span) => Some(span),
+            _ => self.lexeme().map(Lexeme::span),
+        }
+    }
+
+    /// The lexeme that caused the error, if there was one.
+    pub fn lexeme(&self) -> Option<&Lexeme> {
         match *self {
-            Eof => (0, 0),
+            Eof | UnclosedCloseBraces(_) | UnclosedOpenBraces(_) | UnterminatedString(_) => None,
             ExpectedCompCall(ref lexeme) |
             ExpectedVariable(ref lexeme) |
             InvalidComponent(ref lexeme) |
             InvalidElement(ref lexeme) |
+            InvalidFilter(ref lexeme) |
             InvalidFunctionCall(ref lexeme) |
             InvalidTokenInAttributes(ref lexeme) |
+            InvalidLiteral(ref lexeme) |
             NoNameAttachedToClass(ref lexeme) |
             NoNameAttachedToId(ref lexeme) |
             UnexpectedEof(ref lexeme) |
-            UnexpectedToken(ref lexeme) => (lexeme.index(), lexeme.length()),
-            UnclosedCloseBraces(index) | UnclosedOpenBraces(index) => (index, 1),
+            UnexpectedToken(ref lexeme) => Some(lexeme),
         }
     }
 }
@@ -78,17 +105,22 @@ impl error::Error for AstError {
             ExpectedCompCall(_) | InvalidComponent(_) => "Component names can only be words.",
             ExpectedVariable(_) => "Variable names can only be words.",
             InvalidElement(_) => "Element names can only be words.",
+            InvalidFilter(_) => "Filter names can only be words.",
             InvalidFunctionCall(_) => "Function names can only be words.",
             InvalidTokenInAttributes(_) => {
                 "Attributes fields only accept words as single value, or as key-value word pairs, \
                  or a \") which ends the attributes.\""
             }
+            InvalidLiteral(_) => {
+                "Arguments can only be variables, components, strings, numbers, booleans, or null."
+            }
             NoNameAttachedToClass(_) => "Class names can only be words.",
             NoNameAttachedToId(_) => "Id names can only be words.",
             UnclosedCloseBraces(_) => "You have an extra closing brace.",
             UnclosedOpenBraces(_) => "You have an extra open brace.",
             UnexpectedEof(_) => "File ended before an element is finished being parsed",
             UnexpectedToken(_) => "Unknown token in use.",
+            UnterminatedString(_) => "String literal is never closed.",
         }
     }
 }
@@ -103,14 +135,18 @@ impl Display for AstError {
             ExpectedVariable(ref lexeme) |
             InvalidComponent(ref lexeme) |
             InvalidElement(ref lexeme) |
+            InvalidFilter(ref lexeme) |
             InvalidFunctionCall(ref lexeme) |
             InvalidTokenInAttributes(ref lexeme) |
+            InvalidLiteral(ref lexeme) |
             NoNameAttachedToClass(ref lexeme) |
             NoNameAttachedToId(ref lexeme) |
             UnexpectedEof(ref lexeme) |
             UnexpectedToken(ref lexeme) => lexeme,
-            UnclosedCloseBraces(_) | UnclosedOpenBraces(_) => {
-                return write!(f, "{}", self.description())
+            UnclosedCloseBraces(ref span) |
+            UnclosedOpenBraces(ref span) |
+            UnterminatedString(ref span) => {
+                return write!(f, "{} AT: {}", self.description(), span)
             }
 
         };
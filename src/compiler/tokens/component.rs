@@ -1,20 +1,27 @@
+use std::collections::BTreeMap;
 use std::convert::Into;
-use super::ArgKey;
+
+use serde_json::Value;
+use super::{ArgKey, Span};
 use compiler::AstResult;
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Component {
     name: String,
     args: Vec<ArgKey>,
+    defaults: BTreeMap<String, Value>,
     ast: Vec<AstResult>,
+    span: Span,
 }
 
 impl Component {
-    pub fn new(name: String) -> Self {
+    pub fn new(name: String, span: Span) -> Self {
         Component {
             name: name.trim().into(),
             args: Vec::new(),
+            defaults: BTreeMap::new(),
             ast: Vec::new(),
+            span: span,
         }
     }
 
@@ -22,21 +29,49 @@ impl Component {
         &self.name
     }
 
+    /// Where the component's name was written in the source.
+    pub fn span(&self) -> &Span {
+        &self.span
+    }
+
     pub fn args(&self) -> Vec<ArgKey> {
         self.args.clone()
     }
 
+    /// The default values of the parameters that have one.
+    pub fn defaults(&self) -> &BTreeMap<String, Value> {
+        &self.defaults
+    }
+
     pub fn number_of_args(&self) -> usize {
         self.args().len()
     }
 
-    pub fn ast(&self) -> Vec<AstResult> {
-        self.ast.clone()
+    pub fn ast(&self) -> &[AstResult] {
+        &self.ast
     }
 
     pub fn add_arg_value<V: Into<String>>(&mut self, value: V) {
         self.args.push(ArgKey::Json(value.into()));
     }
+
+    /// Adds a parameter that takes JSON, and is `default` when it isn't passed in, such as
+    /// `@kind = "primary"`.
+    pub fn add_arg_default<V: Into<String>>(&mut self, value: V, default: Value) {
+        let value = value.into();
+        self.defaults.insert(value.clone(), default);
+        self.args.push(ArgKey::Json(value));
+    }
+
+    /// Adds a literal argument, which is only valid when the component turns out to be a call.
+    pub fn add_arg_literal(&mut self, value: Value) {
+        self.args.push(ArgKey::Literal(value));
+    }
+
+    /// Adds a parameter that takes a component, such as `&body` in `&card(@title, &body)`.
+    pub fn add_arg_component<V: Into<String>>(&mut self, name: V) {
+        self.args.push(ArgKey::Comp(name.into()));
+    }
     pub fn add_children(&mut self, children: &mut Vec<AstResult>) {
         self.ast.append(children);
     }
@@ -46,13 +81,19 @@ impl Component {
 pub struct ComponentCall {
     name: String,
     values: Vec<ArgKey>,
+    named: BTreeMap<String, ArgKey>,
+    children: Vec<AstResult>,
+    span: Span,
 }
 
 impl ComponentCall {
-    pub fn new(name: String) -> Self {
+    pub fn new(name: String, span: Span) -> Self {
         ComponentCall {
             name: name.trim().into(),
             values: Vec::new(),
+            named: BTreeMap::new(),
+            children: Vec::new(),
+            span: span,
         }
     }
 
@@ -60,6 +101,9 @@ impl ComponentCall {
         ComponentCall {
             name: component.name().into(),
             values: component.args(),
+            named: BTreeMap::new(),
+            children: Vec::new(),
+            span: component.span,
         }
     }
 
@@ -67,11 +111,36 @@ impl ComponentCall {
         &self.name
     }
 
+    /// Where the component was called in the source.
+    pub fn span(&self) -> &Span {
+        &self.span
+    }
+
     pub fn values(&self) -> &[ArgKey] {
         &self.values[..]
     }
 
-    pub fn add_value<V: Into<String>>(&mut self, name: V) {
-        self.values.push(ArgKey::Json(name.into()));
+    /// The arguments passed by name, such as `label = @text`.
+    pub fn named(&self) -> &BTreeMap<String, ArgKey> {
+        &self.named
+    }
+
+    /// The children written in braces after the call, which are passed to the component.
+    pub fn children(&self) -> &[AstResult] {
+        &self.children
+    }
+
+    pub fn add_children(&mut self, children: &mut Vec<AstResult>) {
+        self.children.append(children);
+    }
+
+    /// Passes `value` to the parameter named `key`.
+    pub fn add_named<K: AsRef<str>>(&mut self, key: K, value: ArgKey) {
+        self.named.insert(key.as_ref().trim().into(), value);
+    }
+
+    /// Passes `value` as the next positional argument.
+    pub fn add_argument(&mut self, value: ArgKey) {
+        self.values.push(value);
     }
 }
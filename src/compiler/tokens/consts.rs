@@ -8,6 +8,8 @@ pub const BACKSLASH: char = '\\';
 pub const CARRAGE_RETURN: char = '\r';
 /// The } character used to singify the end of an element.
 pub const CLOSEBRACE: char = '}';
+/// The ] character used to end an index into a variable.
+pub const CLOSEBRACKET: char = ']';
 /// The ) character used to define the end of a function call, or attributes list.
 pub const CLOSEPARAM: char = ')';
 /// The , character used to separate arguments within a component or function.
@@ -16,18 +18,27 @@ pub const COMMA: char = ',';
 pub const DOLLAR: char = '$';
 /// The . character used for defining CSS classes attached to an element.
 pub const DOT: char = '.';
-/// The " character used for values within an attributes field.
+/// The " character used for string literals within an attributes field, or argument list.
 pub const DOUBLEQUOTE: char = '"';
+/// The ! character used after an @ to output a variable without escaping it.
+pub const EXCLAMATION: char = '!';
 /// The = character used for assignment within the attributes field
 pub const EQUALS: char = '=';
-/// The / character used to define elements
+/// The / character used to define elements, and comments. `//` starts a comment running to the
+/// end of the line, and `/*` starts a comment running until `*/`.
 pub const FORWARDSLASH: char = '/';
 /// The { character used to signify the start of an elements children.
 pub const OPENBRACE: char = '{';
+/// The [ character used to index into a variable, such as `@items[0]`, or `@map["key"]`.
+pub const OPENBRACKET: char = '[';
 /// The ( character used to signify the start of the attributes for an element, or start of a 
 /// function call.
 pub const OPENPARAM: char = '(';
+/// The | character used to pass a variable through filters, such as `@name | upper`.
+pub const PIPE: char = '|';
 /// The # character used to define CSS ids for an element.
 pub const POUND: char = '#';
+/// The ' character used for string literals within an attributes field, or argument list.
+pub const SINGLEQUOTE: char = '\'';
 /// The * character used for multi line comments.
 pub const STAR: char = '*';
@@ -1,22 +1,33 @@
 use std::collections::HashMap;
 
 use compiler::AstResult;
-use super::ComponentCall;
+use super::{ComponentCall, Span, Variable};
+
+/// The value of an attribute, or class attached to an element.
+#[derive(Clone, Debug, PartialEq)]
+pub enum AttributeValue {
+    /// Text written directly into the template.
+    Text(String),
+    /// A variable whose value is interpolated into the attribute when rendered.
+    Variable(Variable),
+}
 
 /// The AST representation of a HTML element.
 #[derive(Clone, Debug, PartialEq)]
 pub struct Element {
     tag: String,
-    classes: Vec<String>,
-    attributes: HashMap<String, String>,
+    classes: Vec<AttributeValue>,
+    attributes: HashMap<String, AttributeValue>,
     resource: Option<ComponentCall>,
     children: Vec<AstResult>,
+    span: Span,
 }
 
 impl Element {
-    pub fn new(tag: String) -> Self {
+    pub fn new(tag: String, span: Span) -> Self {
         Element {
             tag: tag,
+            span: span,
             classes: Vec::new(),
             attributes: HashMap::new(),
             resource: None,
@@ -28,11 +39,11 @@ impl Element {
         &*self.tag
     }
 
-    pub fn classes(&self) -> &Vec<String> {
+    pub fn classes(&self) -> &Vec<AttributeValue> {
         &self.classes
     }
 
-    pub fn attributes(&self) -> &HashMap<String, String> {
+    pub fn attributes(&self) -> &HashMap<String, AttributeValue> {
         &self.attributes
     }
 
@@ -44,6 +55,11 @@ impl Element {
         &self.children
     }
 
+    /// Where the element's tag was written in the source.
+    pub fn span(&self) -> &Span {
+        &self.span
+    }
+
     pub fn add_resource(&mut self, resource: ComponentCall) {
         self.resource = Some(resource);
     }
@@ -53,11 +69,19 @@ impl Element {
     }
 
     pub fn add_class(&mut self, class: String) {
-        self.classes.push(class);
+        self.classes.push(AttributeValue::Text(class));
     }
 
     pub fn add_attribute(&mut self, key: String, value: String) {
-        if key == "class" {
+        self.add_attribute_value(key, AttributeValue::Text(value));
+    }
+
+    pub fn add_variable_attribute(&mut self, key: String, variable: Variable) {
+        self.add_attribute_value(key, AttributeValue::Variable(variable));
+    }
+
+    fn add_attribute_value(&mut self, key: String, value: AttributeValue) {
+        if key.trim() == "class" {
             self.classes.push(value);
         } else {
             self.attributes.insert(key.trim().to_owned(), value);
@@ -6,12 +6,17 @@ use super::*;
 pub struct FunctionCall {
     identifier: String,
     arguments: BTreeMap<String, ArgKey>,
+    span: Span,
 }
 
 
 impl FunctionCall {
-    pub fn new(identifier: String) -> Self {
-        FunctionCall { identifier: identifier.trim().to_owned(), ..Self::default() }
+    pub fn new(identifier: String, span: Span) -> Self {
+        FunctionCall {
+            identifier: identifier.trim().to_owned(),
+            span: span,
+            ..Self::default()
+        }
     }
 
     pub fn identifier(&self) -> &str {
@@ -22,11 +27,12 @@ impl FunctionCall {
         &self.arguments
     }
 
-    pub fn add_value_arg<SK: AsRef<str>, SV: Into<String>>(&mut self, key: SK, value: SV) {
-        self.arguments.insert(key.as_ref().trim().into(), ArgKey::Json(value.into()));
+    /// Where the function was called in the source.
+    pub fn span(&self) -> &Span {
+        &self.span
     }
 
-    pub fn add_component_arg<SK: AsRef<str>, SV: Into<String>>(&mut self, key: SK, value: SV) {
-        self.arguments.insert(key.as_ref().trim().into(), ArgKey::Comp(value.into()));
+    pub fn add_arg<SK: AsRef<str>>(&mut self, key: SK, value: ArgKey) {
+        self.arguments.insert(key.as_ref().trim().into(), value);
     }
 }
@@ -3,26 +3,45 @@ use std::fmt::{Display, Formatter};
 
 use self::Lexeme::*;
 use super::operator::Operator;
+use super::Span;
 
-/// Parent enum defining the two types of Terminal symbols within the language.
-/// Words, and operator symbols.
+/// Parent enum defining the types of Terminal symbols within the language.
+/// Words, operator symbols, and comments.
 #[derive(Debug, PartialEq, Clone)]
 pub enum Lexeme {
-    Symbol(usize, Operator),
-    Word(usize, String),
+    Symbol(Span, Operator),
+    Word(Span, String),
+    /// The text of a comment, without its delimiters. The span covers the whole comment.
+    Comment(Span, String),
+    /// The value of a string literal, with its escapes replaced, and the literal as it was
+    /// written. The span covers the quotes.
+    Str(Span, String, String),
 }
 
 impl Lexeme {
     pub fn length(&self) -> usize {
+        self.span().len()
+    }
+
+    pub fn index(&self) -> usize {
+        self.span().start
+    }
+
+    /// Whether the lexeme is a string literal that was never closed, which the lexer gives as
+    /// only its opening quote.
+    pub fn is_unterminated_string(&self) -> bool {
         match *self {
-            Symbol(_, _) => 1,
-            Word(_, ref word) => word.len(),
+            Str(_, _, ref source) => source.len() == 1,
+            _ => false,
         }
     }
 
-    pub fn index(&self) -> usize {
+    pub fn span(&self) -> &Span {
         match *self {
-            Symbol(index, _) | Word(index, _) => index,
+            Symbol(ref span, _) |
+            Word(ref span, _) |
+            Comment(ref span, _) |
+            Str(ref span, _, _) => span,
         }
     }
 }
@@ -32,8 +51,10 @@ impl Display for Lexeme {
         let display = match *self {
             Symbol(_, ref operator) => format!("OPERATOR: {}", operator.to_string()),
             Word(_, ref word) => format!("WORD: {}", word.clone()),
+            Comment(_, ref comment) => format!("COMMENT: {}", comment),
+            Str(_, ref string, _) => format!("STRING: {:?}", string),
         };
 
-        write!(f, "{}", display)
+        write!(f, "{} AT: {}", display, self.span())
     }
 }
@@ -6,6 +6,8 @@ pub mod element;
 pub mod function_call;
 pub mod lexeme;
 pub mod operator;
+pub mod span;
+pub mod variable;
 
 pub use self::args::*;
 pub use self::ast::*;
@@ -15,3 +17,5 @@ pub use self::element::*;
 pub use self::function_call::*;
 pub use self::lexeme::*;
 pub use self::operator::*;
+pub use self::span::*;
+pub use self::variable::*;
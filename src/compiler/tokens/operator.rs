@@ -14,6 +14,8 @@ pub enum Operator {
     BackSlash,
     /// TODO
     CloseBrace,
+    /// The end of an index into a variable.
+    CloseBracket,
     /// TODO
     CloseParam,
     /// TODO
@@ -28,8 +30,12 @@ pub enum Operator {
     ForwardSlash,
     /// TODO
     OpenBrace,
+    /// The start of an index into a variable.
+    OpenBracket,
     /// TODO
     OpenParam,
+    /// Passes a variable to a filter.
+    Pipe,
     /// TODO
     Pound,
     /// TODO
@@ -46,6 +52,7 @@ impl Display for Operator {
             At => AT,
             BackSlash => BACKSLASH,
             CloseBrace => CLOSEBRACE,
+            CloseBracket => CLOSEBRACKET,
             CloseParam => CLOSEPARAM,
             Comma => COMMA,
             Dollar => DOLLAR,
@@ -53,7 +60,9 @@ impl Display for Operator {
             Equals => EQUALS,
             ForwardSlash => FORWARDSLASH,
             OpenBrace => OPENBRACE,
+            OpenBracket => OPENBRACKET,
             OpenParam => OPENPARAM,
+            Pipe => PIPE,
             Pound => POUND,
             Quote => DOUBLEQUOTE,
             Star => STAR,
@@ -0,0 +1,75 @@
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// The location of a piece of source code. `start`, and `end` are byte offsets into the file,
+/// while `line`, and `col` are where the span starts, counting from 1.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Span {
+    /// The file the source was read from, if any.
+    pub file: Option<Arc<PathBuf>>,
+    /// The byte offset of the start of the span.
+    pub start: usize,
+    /// The byte offset of the end of the span.
+    pub end: usize,
+    /// The line the span starts on.
+    pub line: usize,
+    /// The column the span starts on.
+    pub col: usize,
+}
+
+impl Span {
+    /// Creates a span from `start` to `end` in `file`, starting at `line`, and `col`.
+    pub fn new(file: Option<Arc<PathBuf>>,
+               start: usize,
+               end: usize,
+               line: usize,
+               col: usize)
+               -> Self {
+        Span {
+            file: file,
+            start: start,
+            end: end,
+            line: line,
+            col: col,
+        }
+    }
+
+    /// The length of the span in bytes, or 0 if it ends before it starts.
+    pub fn len(&self) -> usize {
+        self.end.saturating_sub(self.start)
+    }
+
+    /// Whether the span doesn't cover any of the source.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Creates a span starting at the start of this span, and finishing at the end of `other`.
+    pub fn to(&self, other: &Span) -> Span {
+        Span { end: other.end, ..self.clone() }
+    }
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self.file {
+            Some(ref file) => write!(f, "{}:{}:{}", file.display(), self.line, self.col),
+            None => write!(f, "{}:{}", self.line, self.col),
+        }
+    }
+}
+
+#[allow(dead_code, unused_imports)]
+mod tests {
+    use super::Span;
+
+    #[test]
+    fn length() {
+        assert_eq!(Span::new(None, 3, 8, 1, 4).len(), 5);
+        assert!(!Span::new(None, 3, 8, 1, 4).is_empty());
+        assert!(Span::new(None, 3, 3, 1, 4).is_empty());
+        assert_eq!(Span::new(None, 8, 3, 1, 9).len(), 0);
+    }
+}
@@ -0,0 +1,151 @@
+use std::mem;
+
+use serde_json;
+use serde_json::Value;
+use super::{ArgKey, Span};
+
+/// The AST representation of a variable being output into the template.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Variable {
+    name: String,
+    raw: bool,
+    filters: Vec<Filter>,
+    span: Span,
+}
+
+impl Variable {
+    pub fn new<S: Into<String>>(name: S, span: Span) -> Self {
+        Variable {
+            name: name.into().trim().to_owned(),
+            raw: false,
+            filters: Vec::new(),
+            span: span,
+        }
+    }
+
+    /// A variable whose value is written out verbatim, without being escaped.
+    pub fn raw<S: Into<String>>(name: S, span: Span) -> Self {
+        Variable { raw: true, ..Variable::new(name, span) }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn is_raw(&self) -> bool {
+        self.raw
+    }
+
+    /// Where the variable was written in the source.
+    pub fn span(&self) -> &Span {
+        &self.span
+    }
+
+    /// The filters the variable is passed through before it is rendered, in order.
+    pub fn filters(&self) -> &[Filter] {
+        &self.filters
+    }
+
+    pub fn add_filter(&mut self, filter: Filter) {
+        self.filters.push(filter);
+    }
+}
+
+/// A filter applied to a variable, such as `truncate(20)` in `@title | truncate(20)`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Filter {
+    name: String,
+    args: Vec<ArgKey>,
+    span: Span,
+}
+
+impl Filter {
+    pub fn new<S: Into<String>>(name: S, span: Span) -> Self {
+        Filter {
+            name: name.into().trim().to_owned(),
+            args: Vec::new(),
+            span: span,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn args(&self) -> &[ArgKey] {
+        &self.args
+    }
+
+    /// Where the filter's name was written in the source.
+    pub fn span(&self) -> &Span {
+        &self.span
+    }
+
+    pub fn add_arg(&mut self, arg: ArgKey) {
+        self.args.push(arg);
+    }
+}
+
+/// A single step along the path of a variable.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Segment {
+    /// A key of an object, such as `.name`, or `["key.with.dots"]`. Keys that are numbers also
+    /// index into arrays, so `.0` is the first item of an array.
+    Key(String),
+    /// An index into an array, such as `[0]`, counting back from the end when it's negative.
+    Index(i64),
+}
+
+/// Splits the path of a variable, such as `items[0].name`, into its segments. Keys in brackets
+/// are written as JSON strings, so they can contain dots, and brackets.
+pub fn path_segments(path: &str) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    let mut key = String::new();
+    let mut characters = path.chars().peekable();
+
+    while let Some(character) = characters.next() {
+        match character {
+            '.' => segments.push(Segment::Key(mem::replace(&mut key, String::new()))),
+            '[' => {
+                if !key.is_empty() {
+                    segments.push(Segment::Key(mem::replace(&mut key, String::new())));
+                }
+
+                let mut index = String::new();
+                let mut in_string = false;
+                let mut escaped = false;
+                while let Some(character) = characters.next() {
+                    match character {
+                        ']' if !in_string => break,
+                        '"' if !escaped => in_string = !in_string,
+                        _ => {}
+                    }
+                    escaped = in_string && character == '\\' && !escaped;
+                    index.push(character);
+                }
+
+                let segment = match index.parse() {
+                    Ok(index) => Segment::Index(index),
+                    Err(_) => {
+                        match serde_json::from_str(&index) {
+                            Ok(Value::String(key)) => Segment::Key(key),
+                            _ => Segment::Key(index),
+                        }
+                    }
+                };
+                segments.push(segment);
+
+                // The dot after the brackets of `items[0].name` doesn't start an empty key.
+                if characters.peek() == Some(&'.') {
+                    let _ = characters.next();
+                }
+            }
+            character => key.push(character),
+        }
+    }
+
+    if !key.is_empty() || segments.is_empty() {
+        segments.push(Segment::Key(key));
+    }
+    segments
+}
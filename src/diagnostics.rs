@@ -0,0 +1,548 @@
+//! Structured diagnostics for the errors a template can produce, and a renderer turning them into
+//! rustc style snippets, plain text, or JSON.
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use serde_json;
+use serde_json::Value;
+
+use compiler::{AstError, CodegenError, Lexeme, Span};
+use template::{LocaleError, TemplateError};
+
+/// How serious a diagnostic is.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Severity {
+    /// The template couldn't be rendered.
+    Error,
+    /// The template was rendered, but probably not how it was intended.
+    Warning,
+    /// Extra information attached to another diagnostic.
+    Note,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Note => "note",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A message attached to a location in the source.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Label {
+    /// Where the label points to.
+    pub span: Span,
+    /// The message shown next to the location.
+    pub message: String,
+    /// Whether this is the location the diagnostic is about, or a related one.
+    pub primary: bool,
+}
+
+/// An error, or warning about a template, along with everything needed to explain it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    /// How serious the diagnostic is.
+    pub severity: Severity,
+    /// A short description of what went wrong.
+    pub message: String,
+    /// The locations in the source the diagnostic is about.
+    pub labels: Vec<Label>,
+    /// Extra information about the diagnostic.
+    pub notes: Vec<String>,
+    /// Suggestions of how to fix the problem.
+    pub suggestions: Vec<String>,
+}
+
+impl Diagnostic {
+    /// Creates a diagnostic with no labels, notes, or suggestions.
+    pub fn new<S: Into<String>>(severity: Severity, message: S) -> Self {
+        Diagnostic {
+            severity: severity,
+            message: message.into(),
+            labels: Vec::new(),
+            notes: Vec::new(),
+            suggestions: Vec::new(),
+        }
+    }
+
+    /// Creates an error diagnostic.
+    pub fn error<S: Into<String>>(message: S) -> Self {
+        Diagnostic::new(Severity::Error, message)
+    }
+
+    /// Creates a warning diagnostic.
+    pub fn warning<S: Into<String>>(message: S) -> Self {
+        Diagnostic::new(Severity::Warning, message)
+    }
+
+    /// Adds the label pointing at the location the diagnostic is about.
+    pub fn primary<S: Into<String>>(mut self, span: Span, message: S) -> Self {
+        self.labels.push(Label {
+            span: span,
+            message: message.into(),
+            primary: true,
+        });
+        self
+    }
+
+    /// Adds a label pointing at a location related to the diagnostic.
+    pub fn secondary<S: Into<String>>(mut self, span: Span, message: S) -> Self {
+        self.labels.push(Label {
+            span: span,
+            message: message.into(),
+            primary: false,
+        });
+        self
+    }
+
+    /// Adds a note.
+    pub fn note<S: Into<String>>(mut self, note: S) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    /// Adds a suggestion of how to fix the problem.
+    pub fn suggestion<S: Into<String>>(mut self, suggestion: S) -> Self {
+        self.suggestions.push(suggestion.into());
+        self
+    }
+
+    /// The span of the primary label, if there is one.
+    pub fn span(&self) -> Option<&Span> {
+        self.labels.iter().find(|label| label.primary).map(|label| &label.span)
+    }
+}
+
+impl<'a> From<&'a AstError> for Diagnostic {
+    fn from(error: &'a AstError) -> Self {
+        use compiler::AstError::*;
+        let diagnostic = Diagnostic::error(error.description());
+
+        let span = match error.span() {
+            Some(span) => span.clone(),
+            None => return diagnostic,
+        };
+
+        match *error {
+            Eof => diagnostic,
+            UnclosedOpenBraces(_) => {
+                diagnostic.primary(span, "this brace is never closed")
+                          .suggestion("add a `}` after the last child")
+            }
+            UnclosedCloseBraces(_) => {
+                diagnostic.primary(span, "this brace has no matching `{`")
+                          .suggestion("remove the `}`, or escape it as `\\}`")
+            }
+            UnexpectedEof(_) => diagnostic.primary(span, "the file ended after this"),
+            UnterminatedString(_) => {
+                diagnostic.primary(span, "this quote is never closed")
+                          .suggestion("add a matching quote at the end of the string")
+            }
+            InvalidTokenInAttributes(ref lexeme) |
+            InvalidLiteral(ref lexeme) |
+            ExpectedCompCall(ref lexeme) |
+            ExpectedVariable(ref lexeme) |
+            InvalidComponent(ref lexeme) |
+            InvalidElement(ref lexeme) |
+            InvalidFilter(ref lexeme) |
+            InvalidFunctionCall(ref lexeme) |
+            NoNameAttachedToClass(ref lexeme) |
+            NoNameAttachedToId(ref lexeme) |
+            UnexpectedToken(ref lexeme) => {
+                diagnostic.primary(span, format!("unexpected {}", lexeme_name(lexeme)))
+            }
+        }
+    }
+}
+
+impl<'a> From<&'a CodegenError> for Diagnostic {
+    fn from(error: &'a CodegenError) -> Self {
+        use compiler::CodegenError::*;
+        let message = error.description().trim_right_matches(&[' ', ':'][..]);
+        let diagnostic = Diagnostic::error(message);
+
+        match *error {
+            AstError(ref error) => Diagnostic::from(error),
+            FromUtf8Error(ref error) => diagnostic.note(error.to_string()),
+            FilterError(ref message, ref span) => {
+                diagnostic.primary(span.clone(), "used here").note(message.clone())
+            }
+            FunctionError(ref message, ref span) => {
+                diagnostic.primary(span.clone(), "called here").note(message.clone())
+            }
+            IoError(ref error) => diagnostic.note(error.to_string()),
+            MissingArgument(ref name, ref span) => {
+                diagnostic.primary(span.clone(), format!("`{}` isn't passed in", name))
+                          .suggestion(format!("pass it with `{} = ...`, or give the parameter a \
+                                               default value",
+                                              name))
+            }
+            MissingCase(ref name, ref span) => {
+                diagnostic.primary(span.clone(), format!("no case for `{}`", name))
+                          .suggestion(format!("add `&{} {{ ... }}`, or `&other {{ ... }}`", name))
+            }
+            NoSuchComponent(ref name, ref span) => {
+                diagnostic.primary(span.clone(), format!("no component named `{}`", name))
+                          .suggestion(format!("define it with `&{} {{ ... }}`, or import the \
+                                               file it is defined in",
+                                              name))
+            }
+            NoSuchFilter(ref name, ref span) => {
+                diagnostic.primary(span.clone(), format!("no filter named `{}`", name))
+                          .suggestion("register the filter with `Template::register_filter`")
+            }
+            NoSuchFunction(ref name, ref span) => {
+                diagnostic.primary(span.clone(), format!("no function named `{}`", name))
+                          .suggestion("register the function with `Template::register`")
+            }
+            NotAnObjectOrNull(ref name, ref span) => {
+                diagnostic.primary(span.clone(),
+                                   format!("`{}` goes into a string, number, or boolean", name))
+            }
+            UndefinedVariable(ref name, ref span) => {
+                diagnostic.primary(span.clone(), format!("`{}` isn't defined", name))
+            }
+            UnknownArgument(ref name, ref span) => {
+                diagnostic.primary(span.clone(), format!("no parameter named `{}`", name))
+            }
+            WrongArgumentType(ref name, ref span) => {
+                diagnostic.primary(span.clone(),
+                                   format!("the argument passed to `{}` is the wrong type", name))
+                          .note("parameters written as `&name` take components, and ones \
+                                 written as `@name` take JSON")
+            }
+            WrongNumberOfArguments(expected, actual, ref span) => {
+                diagnostic.primary(span.clone(),
+                                   format!("expected {} arguments, found {}", expected, actual))
+            }
+        }
+    }
+}
+
+impl<'a> From<&'a TemplateError> for Diagnostic {
+    fn from(error: &'a TemplateError) -> Self {
+        match *error {
+            TemplateError::CodegenError(ref error) => Diagnostic::from(error),
+            TemplateError::InvalidCatalog(ref path, ref message) => {
+                Diagnostic::error(format!("The catalog `{}` couldn't be loaded", path.display()))
+                    .note(message.clone())
+            }
+            TemplateError::NoSuchLocale(ref lang) => {
+                Diagnostic::error(format!("The template has no locale named `{}`", lang))
+                    .suggestion("add the locale, or set one to fall back to with \
+                                 `Template::default_locale`")
+            }
+            TemplateError::PreDefinedComponent => {
+                Diagnostic::error("A component with that name is already defined")
+            }
+            TemplateError::PreDefinedFilter => {
+                Diagnostic::error("A filter with that name is already registered")
+            }
+            TemplateError::PreDefinedLoader => {
+                Diagnostic::error("A locale loader for that extension is already registered")
+            }
+            TemplateError::PreDefinedFunction => {
+                Diagnostic::error("A function with that name is already registered")
+            }
+            TemplateError::IoError(ref error) => Diagnostic::error(error.to_string()),
+        }
+    }
+}
+
+impl<'a> From<&'a LocaleError> for Diagnostic {
+    fn from(error: &'a LocaleError) -> Self {
+        match *error {
+            LocaleError::MissingComponent(ref lang, ref name, ref span) => {
+                Diagnostic::error(format!("The locale `{}` has no component `{}`", lang, name))
+                    .primary(span.clone(), "defined here in the reference locale")
+                    .suggestion(format!("translate it in `{}`, or a locale it falls back to", lang))
+            }
+            LocaleError::ExtraComponent(ref lang, ref name, ref span) => {
+                Diagnostic::error(format!("The component `{}` is only in the locale `{}`",
+                                          name,
+                                          lang))
+                    .primary(span.clone(), "not in the reference locale")
+            }
+            LocaleError::MismatchedArguments(ref lang,
+                                             ref name,
+                                             ref expected,
+                                             ref found,
+                                             ref span) => {
+                Diagnostic::error(format!("The component `{}` takes different parameters in the \
+                                           locale `{}`",
+                                          name,
+                                          lang))
+                    .primary(span.clone(), format!("takes ({})", found.join(", ")))
+                    .note(format!("the reference locale takes ({})", expected.join(", ")))
+            }
+        }
+    }
+}
+
+fn lexeme_name(lexeme: &Lexeme) -> String {
+    match *lexeme {
+        Lexeme::Symbol(_, ref operator) => format!("`{}`", operator),
+        Lexeme::Word(_, ref word) => format!("`{}`", word.trim()),
+        Lexeme::Comment(..) => String::from("comment"),
+        Lexeme::Str(_, ref string, _) => format!("string {:?}", string),
+    }
+}
+
+/// The format a `Renderer` writes diagnostics in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Style {
+    /// Snippets of the source, with the labels underlined, like rustc.
+    Rich,
+    /// A single `file:line:col: severity: message` line, followed by the notes.
+    Plain,
+    /// A JSON object per diagnostic, for tools.
+    Json,
+}
+
+/// Renders diagnostics. Sources that haven't been added to the renderer are read from disk when
+/// a snippet of them is needed.
+pub struct Renderer {
+    style: Style,
+    sources: HashMap<PathBuf, String>,
+}
+
+impl Renderer {
+    /// Creates a renderer, writing diagnostics in `style`.
+    pub fn new(style: Style) -> Self {
+        Renderer {
+            style: style,
+            sources: HashMap::new(),
+        }
+    }
+
+    /// Adds the source of a file, so it doesn't have to be read from disk.
+    pub fn add_source<P: AsRef<Path>, S: Into<String>>(&mut self, file: P, source: S) {
+        self.sources.insert(file.as_ref().to_path_buf(), source.into());
+    }
+
+    /// Renders the diagnostic into a String.
+    pub fn render(&self, diagnostic: &Diagnostic) -> String {
+        match self.style {
+            Style::Rich => self.render_rich(diagnostic),
+            Style::Plain => render_plain(diagnostic),
+            Style::Json => render_json(diagnostic),
+        }
+    }
+
+    fn source(&self, file: &Path) -> Option<String> {
+        if let Some(source) = self.sources.get(file) {
+            return Some(source.clone());
+        }
+
+        let mut source = String::new();
+        match File::open(file) {
+            Ok(mut file) => {
+                match file.read_to_string(&mut source) {
+                    Ok(_) => Some(source),
+                    Err(_) => None,
+                }
+            }
+            Err(_) => None,
+        }
+    }
+
+    fn render_rich(&self, diagnostic: &Diagnostic) -> String {
+        let mut output = format!("{}: {}\n", diagnostic.severity, diagnostic.message);
+
+        let mut labels: Vec<&Label> = diagnostic.labels.iter().collect();
+        labels.sort_by(|a, b| (a.span.line, a.span.col).cmp(&(b.span.line, b.span.col)));
+        let gutter = labels.iter().map(|label| label.span.line.to_string().len()).max();
+        let gutter = gutter.unwrap_or(0);
+        let margin = format!("{:1$} |", "", gutter);
+
+        let mut current_file = None;
+        for label in labels {
+            if current_file != Some(&label.span.file) {
+                output.push_str(&format!("{:1$}--> {2}\n", "", gutter, label.span));
+                current_file = Some(&label.span.file);
+            }
+
+            let source = match label.span.file {
+                Some(ref file) => self.source(file),
+                None => None,
+            };
+            output.push_str(&format!("{}\n", margin));
+            match source {
+                Some(ref source) => {
+                    let (line, underline) = snippet(source, &label.span, label.primary);
+                    output.push_str(&format!("{:>2$} | {}\n", label.span.line, line, gutter));
+                    output.push_str(&format!("{} {} {}\n", margin, underline, label.message));
+                }
+                None => output.push_str(&format!("{} {}\n", margin, label.message)),
+            }
+        }
+
+        if !diagnostic.labels.is_empty() &&
+           !(diagnostic.notes.is_empty() && diagnostic.suggestions.is_empty()) {
+            output.push_str(&format!("{}\n", margin));
+        }
+        for note in &diagnostic.notes {
+            output.push_str(&format!("{:2$} = note: {}\n", "", note, gutter));
+        }
+        for suggestion in &diagnostic.suggestions {
+            output.push_str(&format!("{:2$} = help: {}\n", "", suggestion, gutter));
+        }
+        output
+    }
+}
+
+/// Gets the line `span` starts on, and the underline of the span, padded so it sits beneath it.
+fn snippet(source: &str, span: &Span, primary: bool) -> (String, String) {
+    if span.start > source.len() || !source.is_char_boundary(span.start) {
+        return (String::new(), String::new());
+    }
+    let line_start = source[..span.start].rfind('\n').map(|index| index + 1).unwrap_or(0);
+    let line_end = source[span.start..]
+                       .find('\n')
+                       .map(|index| span.start + index)
+                       .unwrap_or(source.len());
+    let line = source[line_start..line_end].trim_right();
+
+    // Tabs are kept, so the underline lines up however wide they're displayed.
+    let mut underline: String = source[line_start..span.start]
+                                    .chars()
+                                    .map(|ch| if ch == '\t' { '\t' } else { ' ' })
+                                    .collect();
+    let end = if span.end < line_end { span.end } else { line_end };
+    let length = source[span.start..end].trim_right().chars().count();
+    let marker = if primary { '^' } else { '-' };
+
+    for _ in 0..if length == 0 { 1 } else { length } {
+        underline.push(marker);
+    }
+
+    (line.to_owned(), underline)
+}
+
+fn render_plain(diagnostic: &Diagnostic) -> String {
+    let mut output = match diagnostic.span() {
+        Some(span) => format!("{}: {}: {}\n", span, diagnostic.severity, diagnostic.message),
+        None => format!("{}: {}\n", diagnostic.severity, diagnostic.message),
+    };
+
+    // The primary label is where the header already points.
+    for label in diagnostic.labels.iter().filter(|label| !label.primary) {
+        output.push_str(&format!("{}: {}: {}\n", label.span, Severity::Note, label.message));
+    }
+    for note in &diagnostic.notes {
+        output.push_str(&format!("note: {}\n", note));
+    }
+    for suggestion in &diagnostic.suggestions {
+        output.push_str(&format!("help: {}\n", suggestion));
+    }
+    output
+}
+
+fn render_json(diagnostic: &Diagnostic) -> String {
+    let mut object = BTreeMap::new();
+    object.insert(String::from("severity"),
+                  Value::String(diagnostic.severity.to_string()));
+    object.insert(String::from("message"), Value::String(diagnostic.message.clone()));
+
+    let labels = diagnostic.labels
+                           .iter()
+                           .map(|label| {
+                               let mut object = span_to_json(&label.span);
+                               object.insert(String::from("message"),
+                                             Value::String(label.message.clone()));
+                               object.insert(String::from("primary"), Value::Bool(label.primary));
+                               Value::Object(object)
+                           })
+                           .collect();
+    object.insert(String::from("labels"), Value::Array(labels));
+    object.insert(String::from("notes"), strings_to_json(&diagnostic.notes));
+    object.insert(String::from("suggestions"), strings_to_json(&diagnostic.suggestions));
+
+    match serde_json::to_string(&Value::Object(object)) {
+        Ok(json) => json,
+        Err(_) => String::new(),
+    }
+}
+
+fn span_to_json(span: &Span) -> BTreeMap<String, Value> {
+    let mut object = BTreeMap::new();
+    let file = match span.file {
+        Some(ref file) => Value::String(file.display().to_string()),
+        None => Value::Null,
+    };
+    object.insert(String::from("file"), file);
+    object.insert(String::from("start"), Value::U64(span.start as u64));
+    object.insert(String::from("end"), Value::U64(span.end as u64));
+    object.insert(String::from("line"), Value::U64(span.line as u64));
+    object.insert(String::from("col"), Value::U64(span.col as u64));
+    object
+}
+
+fn strings_to_json(strings: &[String]) -> Value {
+    Value::Array(strings.iter().map(|string| Value::String(string.clone())).collect())
+}
+
+#[allow(dead_code, unused_imports)]
+mod tests {
+    use std::path::PathBuf;
+    use std::sync::Arc;
+    use super::{Diagnostic, Renderer, Style};
+    use compiler::Span;
+    use serde_json;
+    use serde_json::Value;
+
+    const SOURCE: &'static str = "/p {\n    &missing()\n}";
+
+    fn diagnostic() -> Diagnostic {
+        let span = Span::new(Some(Arc::new(PathBuf::from("index.polly"))), 10, 17, 2, 6);
+        Diagnostic::error("Component called doesn't exist in the current template")
+            .primary(span, "no component named `missing`")
+            .suggestion("define it with `&missing { ... }`")
+    }
+
+    #[test]
+    fn rich() {
+        let mut renderer = Renderer::new(Style::Rich);
+        renderer.add_source("index.polly", SOURCE);
+
+        assert_eq!(renderer.render(&diagnostic()),
+                   "error: Component called doesn't exist in the current template\n --> \
+                    index.polly:2:6\n  |\n2 |     &missing()\n  |      ^^^^^^^ no component \
+                    named `missing`\n  |\n  = help: define it with `&missing { ... }`\n");
+    }
+
+    #[test]
+    fn plain() {
+        assert_eq!(Renderer::new(Style::Plain).render(&diagnostic()),
+                   "index.polly:2:6: error: Component called doesn't exist in the current \
+                    template\nhelp: define it with `&missing { ... }`\n");
+
+        let span = Span::new(Some(Arc::new(PathBuf::from("index.polly"))), 0, 5, 1, 1);
+        let diagnostic = diagnostic().secondary(span, "called from here");
+        assert_eq!(Renderer::new(Style::Plain).render(&diagnostic),
+                   "index.polly:2:6: error: Component called doesn't exist in the current \
+                    template\nindex.polly:1:1: note: called from here\nhelp: define it with \
+                    `&missing { ... }`\n");
+    }
+
+    #[test]
+    fn json() {
+        let json = Renderer::new(Style::Json).render(&diagnostic());
+        let json: Value = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(json.find("severity"), Some(&Value::String(String::from("error"))));
+        let label = &json.find("labels").unwrap().as_array().unwrap()[0];
+        assert_eq!(label.find("file"), Some(&Value::String(String::from("index.polly"))));
+        assert_eq!(label.find("line"), Some(&Value::U64(2)));
+        assert_eq!(label.find("col"), Some(&Value::U64(6)));
+        assert_eq!(label.find("primary"), Some(&Value::Bool(true)));
+    }
+}
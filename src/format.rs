@@ -0,0 +1,540 @@
+//! The CLDR number, percent, currency, and date formats of locales, for `$std.number`,
+//! `$std.percent`, `$std.currency`, and `$std.date`. Only a bundled set of locales is supported,
+//! and other locales use the formats of English.
+use serde_json::Value;
+
+/// How a locale writes numbers, and dates.
+struct Locale {
+    decimal: &'static str,
+    group: &'static str,
+    /// The size of the groups of digits after the first group of three, two in India.
+    group_size: usize,
+    /// Numbers aren't grouped unless they have more than this many digits past the first group.
+    min_grouping: usize,
+    /// `#` is replaced with the number.
+    percent: &'static str,
+    /// `¤` is replaced with the symbol of the currency, and `#` with the number.
+    currency: &'static str,
+    /// The short, medium, long, and full date patterns.
+    dates: [&'static str; 4],
+    months: [&'static str; 12],
+    short_months: [&'static str; 12],
+    /// Starting on Sunday.
+    days: [&'static str; 7],
+}
+
+const EN: Locale = Locale {
+    decimal: ".",
+    group: ",",
+    group_size: 3,
+    min_grouping: 1,
+    percent: "#%",
+    currency: "¤#",
+    dates: ["M/d/yy", "MMM d, y", "MMMM d, y", "EEEE, MMMM d, y"],
+    months: ["January", "February", "March", "April", "May", "June", "July", "August",
+             "September", "October", "November", "December"],
+    short_months: ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov",
+                   "Dec"],
+    days: ["Sunday", "Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday"],
+};
+
+const EN_GB: Locale = Locale {
+    dates: ["dd/MM/y", "d MMM y", "d MMMM y", "EEEE, d MMMM y"],
+    ..EN
+};
+
+const EN_IN: Locale = Locale {
+    group_size: 2,
+    dates: ["dd/MM/yy", "d MMM y", "d MMMM y", "EEEE, d MMMM, y"],
+    ..EN
+};
+
+const DE: Locale = Locale {
+    decimal: ",",
+    group: ".",
+    group_size: 3,
+    min_grouping: 1,
+    percent: "#\u{a0}%",
+    currency: "#\u{a0}¤",
+    dates: ["dd.MM.yy", "dd.MM.y", "d. MMMM y", "EEEE, d. MMMM y"],
+    months: ["Januar", "Februar", "März", "April", "Mai", "Juni", "Juli", "August", "September",
+             "Oktober", "November", "Dezember"],
+    short_months: ["Jan.", "Feb.", "März", "Apr.", "Mai", "Juni", "Juli", "Aug.", "Sept.", "Okt.",
+                   "Nov.", "Dez."],
+    days: ["Sonntag", "Montag", "Dienstag", "Mittwoch", "Donnerstag", "Freitag", "Samstag"],
+};
+
+const DE_CH: Locale = Locale {
+    decimal: ".",
+    group: "’",
+    percent: "#%",
+    currency: "¤\u{a0}#",
+    ..DE
+};
+
+const FR: Locale = Locale {
+    decimal: ",",
+    group: "\u{202f}",
+    group_size: 3,
+    min_grouping: 1,
+    percent: "#\u{a0}%",
+    currency: "#\u{a0}¤",
+    dates: ["dd/MM/y", "d MMM y", "d MMMM y", "EEEE d MMMM y"],
+    months: ["janvier", "février", "mars", "avril", "mai", "juin", "juillet", "août",
+             "septembre", "octobre", "novembre", "décembre"],
+    short_months: ["janv.", "févr.", "mars", "avr.", "mai", "juin", "juil.", "août", "sept.",
+                   "oct.", "nov.", "déc."],
+    days: ["dimanche", "lundi", "mardi", "mercredi", "jeudi", "vendredi", "samedi"],
+};
+
+const ES: Locale = Locale {
+    decimal: ",",
+    group: ".",
+    group_size: 3,
+    min_grouping: 2,
+    percent: "#\u{a0}%",
+    currency: "#\u{a0}¤",
+    dates: ["d/M/yy", "d MMM y", "d 'de' MMMM 'de' y", "EEEE, d 'de' MMMM 'de' y"],
+    months: ["enero", "febrero", "marzo", "abril", "mayo", "junio", "julio", "agosto",
+             "septiembre", "octubre", "noviembre", "diciembre"],
+    short_months: ["ene", "feb", "mar", "abr", "may", "jun", "jul", "ago", "sept", "oct", "nov",
+                   "dic"],
+    days: ["domingo", "lunes", "martes", "miércoles", "jueves", "viernes", "sábado"],
+};
+
+const IT: Locale = Locale {
+    decimal: ",",
+    group: ".",
+    group_size: 3,
+    min_grouping: 1,
+    percent: "#%",
+    currency: "#\u{a0}¤",
+    dates: ["dd/MM/yy", "d MMM y", "d MMMM y", "EEEE d MMMM y"],
+    months: ["gennaio", "febbraio", "marzo", "aprile", "maggio", "giugno", "luglio", "agosto",
+             "settembre", "ottobre", "novembre", "dicembre"],
+    short_months: ["gen", "feb", "mar", "apr", "mag", "giu", "lug", "ago", "set", "ott", "nov",
+                   "dic"],
+    days: ["domenica", "lunedì", "martedì", "mercoledì", "giovedì", "venerdì", "sabato"],
+};
+
+const NL: Locale = Locale {
+    decimal: ",",
+    group: ".",
+    group_size: 3,
+    min_grouping: 1,
+    percent: "#%",
+    currency: "¤\u{a0}#",
+    dates: ["dd-MM-y", "d MMM y", "d MMMM y", "EEEE d MMMM y"],
+    months: ["januari", "februari", "maart", "april", "mei", "juni", "juli", "augustus",
+             "september", "oktober", "november", "december"],
+    short_months: ["jan", "feb", "mrt", "apr", "mei", "jun", "jul", "aug", "sep", "okt", "nov",
+                   "dec"],
+    days: ["zondag", "maandag", "dinsdag", "woensdag", "donderdag", "vrijdag", "zaterdag"],
+};
+
+const PT: Locale = Locale {
+    decimal: ",",
+    group: ".",
+    group_size: 3,
+    min_grouping: 1,
+    percent: "#%",
+    currency: "¤\u{a0}#",
+    dates: ["dd/MM/y", "d 'de' MMM 'de' y", "d 'de' MMMM 'de' y", "EEEE, d 'de' MMMM 'de' y"],
+    months: ["janeiro", "fevereiro", "março", "abril", "maio", "junho", "julho", "agosto",
+             "setembro", "outubro", "novembro", "dezembro"],
+    short_months: ["jan.", "fev.", "mar.", "abr.", "mai.", "jun.", "jul.", "ago.", "set.",
+                   "out.", "nov.", "dez."],
+    days: ["domingo", "segunda-feira", "terça-feira", "quarta-feira", "quinta-feira",
+           "sexta-feira", "sábado"],
+};
+
+const RU: Locale = Locale {
+    decimal: ",",
+    group: "\u{a0}",
+    group_size: 3,
+    min_grouping: 1,
+    percent: "#\u{a0}%",
+    currency: "#\u{a0}¤",
+    dates: ["dd.MM.y", "d MMM y 'г'.", "d MMMM y 'г'.", "EEEE, d MMMM y 'г'."],
+    months: ["января", "февраля", "марта", "апреля",
+             "мая", "июня", "июля", "августа",
+             "сентября", "октября", "ноября", "декабря"],
+    short_months: ["янв.", "февр.", "мар.", "апр.", "мая", "июн.",
+                   "июл.", "авг.", "сент.", "окт.", "нояб.", "дек."],
+    days: ["воскресенье", "понедельник", "вторник", "среда",
+           "четверг", "пятница", "суббота"],
+};
+
+const PL: Locale = Locale {
+    decimal: ",",
+    group: "\u{a0}",
+    group_size: 3,
+    min_grouping: 2,
+    percent: "#%",
+    currency: "#\u{a0}¤",
+    dates: ["d.MM.y", "d MMM y", "d MMMM y", "EEEE, d MMMM y"],
+    months: ["stycznia", "lutego", "marca", "kwietnia", "maja", "czerwca", "lipca", "sierpnia",
+             "września", "października", "listopada", "grudnia"],
+    short_months: ["sty", "lut", "mar", "kwi", "maj", "cze", "lip", "sie", "wrz", "paź", "lis",
+                   "gru"],
+    days: ["niedziela", "poniedziałek", "wtorek", "środa", "czwartek", "piątek", "sobota"],
+};
+
+const SV: Locale = Locale {
+    decimal: ",",
+    group: "\u{a0}",
+    group_size: 3,
+    min_grouping: 1,
+    percent: "#\u{a0}%",
+    currency: "#\u{a0}¤",
+    dates: ["y-MM-dd", "d MMM y", "d MMMM y", "EEEE d MMMM y"],
+    months: ["januari", "februari", "mars", "april", "maj", "juni", "juli", "augusti",
+             "september", "oktober", "november", "december"],
+    short_months: ["jan.", "feb.", "mars", "apr.", "maj", "juni", "juli", "aug.", "sep.",
+                   "okt.", "nov.", "dec."],
+    days: ["söndag", "måndag", "tisdag", "onsdag", "torsdag", "fredag", "lördag"],
+};
+
+const JA: Locale = Locale {
+    decimal: ".",
+    group: ",",
+    group_size: 3,
+    min_grouping: 1,
+    percent: "#%",
+    currency: "¤#",
+    dates: ["y/MM/dd", "y/MM/dd", "y年M月d日", "y年M月d日EEEE"],
+    months: ["1月", "2月", "3月", "4月", "5月", "6月", "7月", "8月",
+             "9月", "10月", "11月", "12月"],
+    short_months: ["1月", "2月", "3月", "4月", "5月", "6月", "7月", "8月",
+                   "9月", "10月", "11月", "12月"],
+    days: ["日曜日", "月曜日", "火曜日", "水曜日",
+           "木曜日", "金曜日", "土曜日"],
+};
+
+const ZH: Locale = Locale {
+    dates: ["y/M/d", "y年M月d日", "y年M月d日", "y年M月d日EEEE"],
+    days: ["星期日", "星期一", "星期二", "星期三",
+           "星期四", "星期五", "星期六"],
+    ..JA
+};
+
+/// The currencies with a symbol, and the number of digits after the decimal point they're
+/// written with. Other currencies are written with their code, and two digits.
+const CURRENCIES: &'static [(&'static str, &'static str, usize)] = &[("BRL", "R$", 2),
+                                                                     ("CHF", "CHF", 2),
+                                                                     ("CNY", "CN¥", 2),
+                                                                     ("EUR", "€", 2),
+                                                                     ("GBP", "£", 2),
+                                                                     ("INR", "₹", 2),
+                                                                     ("JPY", "¥", 0),
+                                                                     ("KRW", "₩", 0),
+                                                                     ("PLN", "zł", 2),
+                                                                     ("RUB", "₽", 2),
+                                                                     ("SEK", "kr", 2),
+                                                                     ("USD", "$", 2)];
+
+/// The formats of `lang`, or of its language, or English if neither is bundled.
+fn locale(lang: &str) -> &'static Locale {
+    let lang = lang.replace('_', "-").to_lowercase();
+    let language = lang.split('-').next().unwrap_or("");
+
+    match (&*lang, language) {
+        ("en-gb", _) | ("en-au", _) | ("en-ie", _) | ("en-nz", _) => &EN_GB,
+        ("en-in", _) => &EN_IN,
+        ("de-ch", _) | ("de-li", _) => &DE_CH,
+        (_, "de") => &DE,
+        (_, "fr") => &FR,
+        (_, "es") => &ES,
+        (_, "it") => &IT,
+        (_, "nl") => &NL,
+        (_, "pt") => &PT,
+        (_, "ru") => &RU,
+        (_, "pl") => &PL,
+        (_, "sv") => &SV,
+        (_, "ja") => &JA,
+        (_, "zh") => &ZH,
+        _ => &EN,
+    }
+}
+
+/// The years dates can be in, so the arithmetic on them can't overflow.
+const MAX_YEAR: i64 = 9999;
+
+/// A number, or a string containing one, as a float. Infinity, and NaN aren't numbers.
+pub fn to_number(value: &Value) -> Option<f64> {
+    let number = match *value {
+        Value::String(ref string) => string.trim().parse().ok(),
+        ref value => value.as_f64(),
+    };
+    number.and_then(|number: f64| if number.is_finite() { Some(number) } else { None })
+}
+
+/// Writes `value` the way `lang` does, with at least `min_fraction`, and at most `max_fraction`
+/// digits after the decimal point.
+pub fn number(lang: &str, value: f64, min_fraction: usize, max_fraction: usize) -> String {
+    let locale = locale(lang);
+    let rounded = format!("{:.*}", max_fraction, value.abs());
+    let (integer, fraction) = match rounded.find('.') {
+        Some(index) => (&rounded[..index], &rounded[index + 1..]),
+        None => (&*rounded, ""),
+    };
+
+    let mut fraction = fraction.to_owned();
+    while fraction.len() > min_fraction && fraction.ends_with('0') {
+        fraction.pop();
+    }
+
+    // Groups are found from the right, the first being three digits, and the rest `group_size`.
+    let mut groups = Vec::new();
+    let mut rest = integer;
+    if rest.len() >= 3 + locale.min_grouping {
+        let mut size = 3;
+        while rest.len() > size {
+            let (head, tail) = rest.split_at(rest.len() - size);
+            groups.push(tail);
+            rest = head;
+            size = locale.group_size;
+        }
+    }
+    groups.push(rest);
+    groups.reverse();
+
+    let mut text = String::new();
+    let negative = value < 0.0 && (integer.chars().chain(fraction.chars()).any(|c| c != '0'));
+    if negative {
+        text.push('-');
+    }
+    text.push_str(&groups.join(locale.group));
+    if !fraction.is_empty() {
+        text.push_str(locale.decimal);
+        text.push_str(&fraction);
+    }
+    text
+}
+
+/// Writes `value` as a percentage the way `lang` does, so `0.25` is "25%", with `decimals` digits
+/// after the decimal point.
+pub fn percent(lang: &str, value: f64, decimals: usize) -> String {
+    locale(lang).percent.replace('#', &number(lang, value * 100.0, decimals, decimals))
+}
+
+/// Writes `value` in the currency with the ISO 4217 code `code`, the way `lang` does. The number
+/// of digits after the decimal point is the currency's, unless `decimals` is given.
+pub fn currency(lang: &str, value: f64, code: &str, decimals: Option<usize>) -> String {
+    let code = code.trim().to_uppercase();
+    let (symbol, digits) = match CURRENCIES.iter().find(|&&(known, _, _)| known == code) {
+        Some(&(_, symbol, digits)) => (symbol.to_owned(), digits),
+        None => (code.clone(), 2),
+    };
+    let digits = decimals.unwrap_or(digits);
+    let number = number(lang, value, digits, digits);
+
+    // A symbol made of letters, such as "CHF", is kept apart from the number next to it.
+    let pattern = locale(lang).currency;
+    let pattern = if pattern == "¤#" && symbol.chars().last().map_or(false, char::is_alphabetic) {
+        "¤\u{a0}#"
+    } else if pattern == "#¤" && symbol.chars().next().map_or(false, char::is_alphabetic) {
+        "#\u{a0}¤"
+    } else {
+        pattern
+    };
+    pattern.replace('¤', &symbol).replace('#', &number)
+}
+
+/// The date pattern `lang` uses for the style `short`, `medium`, `long`, or `full`.
+pub fn date_pattern(lang: &str, style: &str) -> Option<&'static str> {
+    let dates = &locale(lang).dates;
+    match style {
+        "short" => Some(dates[0]),
+        "medium" => Some(dates[1]),
+        "long" => Some(dates[2]),
+        "full" => Some(dates[3]),
+        _ => None,
+    }
+}
+
+/// Writes the date `value` with the CLDR date pattern `pattern`, in the language of `lang`. The
+/// date is either a string starting with an ISO 8601 date, such as "2016-03-04", or
+/// "2016-03-04T12:00:00Z", whose time is ignored, or a Unix timestamp in seconds, in UTC.
+///
+/// In the pattern `y` is the year, `yy` its last two digits, `M`, and `MM` the month as a number,
+/// `MMM`, and `MMMM` its abbreviated, and full name, `d`, and `dd` the day, and `EEEE` the day of
+/// the week. Text between single quotes is written as is.
+pub fn date(lang: &str, value: &Value, pattern: &str) -> Result<String, String> {
+    let (year, month, day) = match *value {
+        Value::String(ref string) => {
+            match parse_date(string) {
+                Some(date) => date,
+                None => return Err(format!("{:?} isn't an ISO 8601 date", string)),
+            }
+        }
+        ref value => {
+            let days = value.as_f64().map(|seconds| (seconds / 86400.0).floor());
+            let min = days_from_civil(-MAX_YEAR, 1, 1) as f64;
+            let max = days_from_civil(MAX_YEAR, 12, 31) as f64;
+            match days {
+                Some(days) if days >= min && days <= max => civil_from_days(days as i64),
+                Some(_) => {
+                    return Err(format!("{:?} is a timestamp outside of the years -{} to {}",
+                                       value,
+                                       MAX_YEAR,
+                                       MAX_YEAR))
+                }
+                None => return Err(format!("{:?} isn't a date, or a timestamp", value)),
+            }
+        }
+    };
+    let locale = locale(lang);
+    // The 1st of January 1970 was a Thursday.
+    let weekday = ((days_from_civil(year, month, day) % 7 + 11) % 7) as usize;
+
+    let mut text = String::new();
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\'' {
+            if chars.peek() == Some(&'\'') {
+                let _ = chars.next();
+                text.push('\'');
+                continue;
+            }
+            while let Some(c) = chars.next() {
+                if c == '\'' {
+                    break;
+                }
+                text.push(c);
+            }
+            continue;
+        }
+
+        let mut count = 1;
+        while c.is_alphabetic() && chars.peek() == Some(&c) {
+            let _ = chars.next();
+            count += 1;
+        }
+        match (c, count) {
+            ('y', 2) => text.push_str(&format!("{:02}", year.abs() % 100)),
+            ('y', _) => text.push_str(&year.to_string()),
+            ('M', 1) => text.push_str(&month.to_string()),
+            ('M', 2) => text.push_str(&format!("{:02}", month)),
+            ('M', 3) => text.push_str(locale.short_months[month as usize - 1]),
+            ('M', _) => text.push_str(locale.months[month as usize - 1]),
+            ('d', 1) => text.push_str(&day.to_string()),
+            ('d', _) => text.push_str(&format!("{:02}", day)),
+            ('E', 4) => text.push_str(locale.days[weekday]),
+            ('E', _) => text.extend(locale.days[weekday].chars().take(3)),
+            (c, count) => {
+                for _ in 0..count {
+                    text.push(c);
+                }
+            }
+        }
+    }
+    Ok(text)
+}
+
+/// The year, month, and day at the start of an ISO 8601 date.
+fn parse_date(string: &str) -> Option<(i64, u32, u32)> {
+    let string = string.trim();
+    let date = string.split(|c| c == 'T' || c == 't' || c == ' ').next().unwrap_or("");
+    let parts: Vec<&str> = date.split('-').collect();
+    if parts.len() != 3 {
+        return None;
+    }
+
+    match (parts[0].parse(), parts[1].parse(), parts[2].parse()) {
+        (Ok(year), Ok(month), Ok(day)) => {
+            if year <= MAX_YEAR && month >= 1 && month <= 12 && day >= 1 &&
+               day <= days_in_month(year, month) {
+                Some((year, month, day))
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// The number of days from the 1st of January 1970 to the date, in the proleptic Gregorian
+/// calendar.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = (if year >= 0 { year } else { year - 399 }) / 400;
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 -
+                      1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// The date `days` days after the 1st of January 1970.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = (if days >= 0 { days } else { days - 146096 }) / 146097;
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 -
+                       day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = (if month_index < 10 { month_index + 3 } else { month_index - 9 }) as u32;
+    let year = year_of_era + era * 400 + (if month <= 2 { 1 } else { 0 });
+    (year, month, day)
+}
+
+#[allow(dead_code, unused_imports)]
+mod tests {
+    use serde_json::Value;
+    use super::{currency, date, date_pattern, number, percent, to_number};
+
+    #[test]
+    fn numbers() {
+        assert_eq!(number("en", 1234567.891, 0, 3), "1,234,567.891");
+        assert_eq!(number("de", 1234567.891, 0, 2), "1.234.567,89");
+        assert_eq!(number("de-CH", 1234.5, 2, 2), "1’234.50");
+        assert_eq!(number("en-IN", 12345678.0, 0, 3), "1,23,45,678");
+        assert_eq!(number("es", 1234.0, 0, 3), "1234");
+        assert_eq!(number("es", 12345.0, 0, 3), "12.345");
+        assert_eq!(number("en", -0.0001, 0, 3), "0");
+        assert_eq!(number("en", -12.5, 0, 3), "-12.5");
+        assert_eq!(to_number(&Value::String(String::from("NaN"))), None);
+        assert_eq!(to_number(&Value::String(String::from("inf"))), None);
+        assert_eq!(to_number(&Value::String(String::from(" 2.5 "))), Some(2.5));
+        assert_eq!(percent("fr", 0.256, 0), "26\u{a0}%");
+        assert_eq!(currency("en", 1234.5, "usd", None), "$1,234.50");
+        assert_eq!(currency("de", 1234.5, "EUR", None), "1.234,50\u{a0}€");
+        assert_eq!(currency("ja", 1234.0, "JPY", None), "¥1,234");
+        assert_eq!(currency("en", 12.0, "CHF", None), "CHF\u{a0}12.00");
+        assert_eq!(currency("en", 12.0, "XYZ", Some(0)), "XYZ\u{a0}12");
+    }
+
+    #[test]
+    fn dates() {
+        let day = Value::String(String::from("2016-03-04T12:00:00Z"));
+        let long = |lang| date(lang, &day, date_pattern(lang, "long").unwrap()).unwrap();
+
+        assert_eq!(long("en"), "March 4, 2016");
+        assert_eq!(long("de"), "4. März 2016");
+        assert_eq!(long("es"), "4 de marzo de 2016");
+        assert_eq!(long("ru"), "4 марта 2016 г.");
+        assert_eq!(long("ja"), "2016年3月4日");
+        assert_eq!(date("en", &day, "EEEE, dd/MM/yy").unwrap(), "Friday, 04/03/16");
+        assert_eq!(date("en", &Value::U64(0), "y-MM-dd EEEE").unwrap(), "1970-01-01 Thursday");
+        assert_eq!(date("en", &Value::I64(-86400), "y-MM-dd").unwrap(), "1969-12-31");
+        assert_eq!(date("en", &Value::U64(951782400), "y-MM-dd").unwrap(), "2000-02-29");
+        assert!(date("en", &Value::String(String::from("2016-02-30")), "y").is_err());
+        assert!(date("en", &Value::F64(1e300), "y").is_err());
+        assert!(date("en", &Value::F64(-1e300), "y").is_err());
+        let huge = Value::String(String::from("99999999999999999-01-01"));
+        assert!(date("en", &huge, "y").is_err());
+    }
+}
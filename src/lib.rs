@@ -8,8 +8,14 @@ extern crate lazy_static;
 extern crate serde;
 extern crate serde_json;
 
+mod catalog;
 mod compiler;
+mod diagnostics;
+mod format;
+mod plural;
 mod template;
 
-pub use template::{PollyFn, std_functions, Template, TemplateError};
-pub use compiler::{ArgValue, AstError, CodegenError};
+pub use template::{CompiledTemplate, LocaleError, LocaleLoader, PollyFilter, PollyFn,
+                   std_filters, std_functions, std_loaders, Template, TemplateError, WarningFn};
+pub use compiler::{ArgValue, AstError, Codegen, CodegenError, Span};
+pub use diagnostics::{Diagnostic, Label, Renderer, Severity, Style};
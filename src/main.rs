@@ -5,16 +5,22 @@ extern crate serde_json;
 
 use std::collections::BTreeMap;
 use std::fs::{File, metadata};
-use std::io::{Read, Write};
+use std::io;
+use std::io::{BufWriter, Read, Write};
+use std::process;
 
-use clap::App;
-use polly::Template;
+use clap::{App, ArgMatches};
+use polly::{CompiledTemplate, Diagnostic, Renderer, Severity, Style, Template, TemplateError};
 use serde_json::Value;
 
 fn main() {
     let yaml = load_yaml!("../cli.yml");
     let matches = App::from_yaml(yaml).get_matches();
 
+    if let Some(matches) = matches.subcommand_matches("check") {
+        check(matches);
+    }
+
     let paths = matches.values_of("input").unwrap();
 
     for path in paths {
@@ -43,22 +49,133 @@ fn main() {
                 BTreeMap::new()
             };
 
-            let html = if matches.is_present("no-locales") {
-                Template::load(path).unwrap().json(json).no_locales().render(lang).unwrap()
+            let template = Template::load(path)
+                               .unwrap()
+                               .html_comments(matches.is_present("html-comments"))
+                               .locale_fallback(!matches.is_present("no-locale-fallback"));
+            let template = if matches.is_present("no-locales") {
+                template.no_locales()
             } else {
-                Template::load(path).unwrap().json(json).render(lang).unwrap()
+                template
+            };
+            let template = match matches.value_of("default-locale") {
+                Some(default_locale) => template.default_locale(default_locale),
+                None => template,
+            };
+
+            let style = error_style(&matches);
+            let renderer = template.renderer(style);
+
+            // Undefined variables are printed as warnings, unless they're errors in strict mode.
+            let warnings = template.renderer(style);
+            let template = template.strict(matches.is_present("strict"))
+                                   .on_warning(Box::new(move |warning| {
+                                       let mut diagnostic = Diagnostic::from(warning);
+                                       diagnostic.severity = Severity::Warning;
+                                       let _ = writeln!(io::stderr(),
+                                                        "{}",
+                                                        warnings.render(&diagnostic).trim_right());
+                                   }));
+
+            let template = match template.compile() {
+                Ok(template) => template,
+                Err(error) => exit_with(&renderer, &[Diagnostic::from(&error)]),
             };
 
-            if let Some(path) = matches.value_of("file") {
-                let mut file = File::create(path)
-                                   .ok()
-                                   .expect("Couldn't create file at destination");
-                file.write_all(&html.into_bytes()).ok().expect("Couldn't write to file");
+            let errors = template.validate();
+            if !errors.is_empty() {
+                let diagnostics: Vec<Diagnostic> = errors.iter().map(Diagnostic::from).collect();
+                exit_with(&renderer, &diagnostics);
+            }
+
+            let result = if let Some(path) = matches.value_of("file") {
+                let file = File::create(path)
+                               .ok()
+                               .expect("Couldn't create file at destination");
+                render_buffered(&template, lang, &json, file)
             } else {
-                println!("{}", html);
+                let stdout = io::stdout();
+                let result = render_buffered(&template, lang, &json, stdout.lock());
+                println!("");
+                result
+            };
+
+            if let Err(error) = result {
+                exit_with(&renderer, &[Diagnostic::from(&error)]);
             }
         } else {
             panic!("Path provided wasn't a file: {}", path);
         }
     }
 }
+
+/// Renders the template through a `BufWriter`, so the HTML isn't written a piece at a time.
+fn render_buffered<W: Write>(template: &CompiledTemplate,
+                             lang: &str,
+                             json: &BTreeMap<String, Value>,
+                             writer: W)
+                             -> Result<(), TemplateError> {
+    let mut writer = BufWriter::new(writer);
+
+    match template.render_to(lang, json, &mut writer) {
+        Ok(()) => {}
+        Err(error) => return Err(error),
+    }
+
+    match writer.flush() {
+        Ok(()) => Ok(()),
+        Err(error) => Err(TemplateError::IoError(error)),
+    }
+}
+
+/// Checks the locales of each template against the reference locale, printing what's missing,
+/// extra, or takes different arguments, and exits with a non-zero status if anything was found.
+fn check(matches: &ArgMatches) -> ! {
+    let reference = matches.value_of("reference").unwrap_or("en");
+    let style = error_style(matches);
+    let mut failed = false;
+
+    for path in matches.values_of("input").unwrap() {
+        let template = Template::load(path)
+                           .unwrap()
+                           .locale_fallback(!matches.is_present("no-locale-fallback"));
+        let template = match matches.value_of("locales-dir") {
+            Some(locales_dir) => template.locales_dir(locales_dir),
+            None => template,
+        };
+        let renderer = template.renderer(style);
+
+        let template = match template.compile() {
+            Ok(template) => template,
+            Err(error) => exit_with(&renderer, &[Diagnostic::from(&error)]),
+        };
+        let errors = match template.check_locales(reference) {
+            Ok(errors) => errors,
+            Err(error) => exit_with(&renderer, &[Diagnostic::from(&error)]),
+        };
+
+        for error in &errors {
+            let diagnostic = Diagnostic::from(error);
+            let _ = writeln!(io::stderr(), "{}", renderer.render(&diagnostic).trim_right());
+        }
+        failed = failed || !errors.is_empty();
+    }
+    process::exit(if failed { 1 } else { 0 })
+}
+
+/// The format errors are printed in, from `--error-format`.
+fn error_style(matches: &ArgMatches) -> Style {
+    match matches.value_of("error-format") {
+        Some("plain") => Style::Plain,
+        Some("json") => Style::Json,
+        _ => Style::Rich,
+    }
+}
+
+/// Prints the diagnostics to stderr, and exits with a non-zero status.
+fn exit_with(renderer: &Renderer, diagnostics: &[Diagnostic]) -> ! {
+    for diagnostic in diagnostics {
+        let _ = writeln!(io::stderr(), "{}", renderer.render(diagnostic).trim_right());
+    }
+    process::exit(1)
+}
@@ -0,0 +1,169 @@
+//! The CLDR plural rules, picking the plural category of a number in a language, for
+//! `&std.plural`.
+use serde_json::Value;
+
+/// The plural category every language has, used when no other category applies.
+pub const OTHER: &'static str = "other";
+
+/// The parts of a number that the plural rules look at, as CLDR defines them. `n` is the absolute
+/// value, `i` its integer digits, `v` the number of fraction digits written, and `f` those digits.
+struct Operands {
+    n: f64,
+    i: u64,
+    v: usize,
+    f: u64,
+}
+
+impl Operands {
+    fn new(value: &Value) -> Option<Self> {
+        let text = match *value {
+            Value::I64(number) => number.to_string(),
+            Value::U64(number) => number.to_string(),
+            Value::F64(number) => number.to_string(),
+            Value::String(ref string) if string.trim().parse::<f64>().is_ok() => {
+                string.trim().to_owned()
+            }
+            _ => return None,
+        };
+        let text = text.trim_left_matches('-');
+
+        let (integer, fraction) = match text.find('.') {
+            Some(index) => (&text[..index], &text[index + 1..]),
+            None => (text, ""),
+        };
+
+        match text.parse::<f64>() {
+            Ok(n) => {
+                Some(Operands {
+                    n: n,
+                    i: integer.parse().unwrap_or(u64::max_value()),
+                    v: fraction.len(),
+                    f: fraction.parse().unwrap_or(0),
+                })
+            }
+            Err(_) => None,
+        }
+    }
+}
+
+/// Whether `value` is from `low` to `high`, inclusive.
+fn between(value: u64, low: u64, high: u64) -> bool {
+    value >= low && value <= high
+}
+
+/// The plural category, `zero`, `one`, `two`, `few`, `many`, or `other`, of `value` in the
+/// language of the locale `lang`. Languages without rules here use the English rules. Returns
+/// `None` if `value` isn't a number.
+pub fn plural_category(lang: &str, value: &Value) -> Option<&'static str> {
+    let Operands { n, i, v, f } = match Operands::new(value) {
+        Some(operands) => operands,
+        None => return None,
+    };
+    let language = lang.split(|c| c == '-' || c == '_').next().unwrap_or("").to_lowercase();
+
+    let category = match &*language {
+        "ja" | "ko" | "zh" | "vi" | "th" | "id" | "ms" | "lo" | "my" => OTHER,
+        "fr" | "pt" => if i == 0 || i == 1 { "one" } else { OTHER },
+        "es" | "el" | "hu" | "tr" | "nb" | "no" | "bg" => if n == 1.0 { "one" } else { OTHER },
+        "da" => if n == 1.0 || (f != 0 && (i == 0 || i == 1)) { "one" } else { OTHER },
+        "ru" | "uk" | "be" => {
+            if v == 0 && i % 10 == 1 && i % 100 != 11 {
+                "one"
+            } else if v == 0 && between(i % 10, 2, 4) && !between(i % 100, 12, 14) {
+                "few"
+            } else if v == 0 {
+                "many"
+            } else {
+                OTHER
+            }
+        }
+        "pl" => {
+            if i == 1 && v == 0 {
+                "one"
+            } else if v == 0 && between(i % 10, 2, 4) && !between(i % 100, 12, 14) {
+                "few"
+            } else if v == 0 {
+                "many"
+            } else {
+                OTHER
+            }
+        }
+        "cs" | "sk" => {
+            if i == 1 && v == 0 {
+                "one"
+            } else if between(i, 2, 4) && v == 0 {
+                "few"
+            } else if v != 0 {
+                "many"
+            } else {
+                OTHER
+            }
+        }
+        "he" | "iw" => {
+            if i == 1 && v == 0 {
+                "one"
+            } else if i == 2 && v == 0 {
+                "two"
+            } else {
+                OTHER
+            }
+        }
+        "ar" => {
+            if n == 0.0 {
+                "zero"
+            } else if n == 1.0 {
+                "one"
+            } else if n == 2.0 {
+                "two"
+            } else if v == 0 && between(i % 100, 3, 10) {
+                "few"
+            } else if v == 0 && between(i % 100, 11, 99) {
+                "many"
+            } else {
+                OTHER
+            }
+        }
+        _ => if i == 1 && v == 0 { "one" } else { OTHER },
+    };
+    Some(category)
+}
+
+/// The plural categories of the forms of a gettext plural in the language of `lang`, in the
+/// order the usual `Plural-Forms` of the language puts them.
+pub fn gettext_categories(lang: &str) -> &'static [&'static str] {
+    let language = lang.split(|c| c == '-' || c == '_').next().unwrap_or("").to_lowercase();
+
+    match &*language {
+        "ja" | "ko" | "zh" | "vi" | "th" | "id" | "ms" | "lo" | "my" => &[OTHER],
+        "ru" | "uk" | "be" | "pl" => &["one", "few", "many"],
+        "cs" | "sk" => &["one", "few", OTHER],
+        "ar" => &["zero", "one", "two", "few", "many", OTHER],
+        _ => &["one", OTHER],
+    }
+}
+
+#[allow(dead_code, unused_imports)]
+mod tests {
+    use serde_json::Value;
+    use super::plural_category;
+
+    fn categories(lang: &str, numbers: &[u64]) -> Vec<&'static str> {
+        numbers.iter().map(|&n| plural_category(lang, &Value::U64(n)).unwrap()).collect()
+    }
+
+    #[test]
+    fn plural_rules() {
+        assert_eq!(categories("en-GB", &[0, 1, 2]), vec!["other", "one", "other"]);
+        assert_eq!(plural_category("en", &Value::F64(1.5)), Some("other"));
+        assert_eq!(plural_category("en", &Value::String(String::from("1.0"))), Some("other"));
+        assert_eq!(categories("fr", &[0, 1, 2]), vec!["one", "one", "other"]);
+        assert_eq!(categories("ru", &[1, 3, 5, 11, 21, 22, 112]),
+                   vec!["one", "few", "many", "many", "one", "few", "many"]);
+        assert_eq!(categories("pl", &[1, 2, 5, 21, 22]),
+                   vec!["one", "few", "many", "many", "few"]);
+        assert_eq!(categories("ar", &[0, 1, 2, 3, 11, 100]),
+                   vec!["zero", "one", "two", "few", "many", "other"]);
+        assert_eq!(categories("ja", &[1]), vec!["other"]);
+        assert_eq!(plural_category("en", &Value::Bool(true)), None);
+    }
+}
@@ -1,18 +1,47 @@
-use std::cell::RefCell;
-use std::collections::{HashMap, BTreeMap};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet, BTreeMap};
+use std::fmt;
+use std::fs;
 use std::fs::File;
 use std::io;
 use std::io::Read;
+use std::mem;
 use std::path::{Path, PathBuf};
-use std::rc::Rc;
+use std::str;
+use std::sync::Arc;
 
+use serde_json;
 use serde_json::Value;
 
-use compiler::{ArgValue, AstError, Codegen, CodegenError, CodegenResult, Component, Lexer, Parser};
+use catalog;
+use compiler::{ArgKey, ArgValue, AstError, AstResult, Codegen, CodegenError, Component,
+               escape_html, Lexeme, Lexer, Operator, Parser, Span, value_to_string};
+use diagnostics::{Diagnostic, Renderer, Style};
+use format;
 
-/// A type abstracting the functions used for Polly.
-pub type PollyFn = Box<Fn(BTreeMap<String, ArgValue>, &Rc<RefCell<Template>>)
-                          -> Result<String, String>>;
+/// A type abstracting the functions used for Polly. The `Codegen` passed in is the one rendering
+/// the function call, and can be used to render any components passed to the function, or to get
+/// the locale being rendered with `Codegen::lang`.
+/// Functions have to be `Send`, and `Sync` so that a template can be shared between threads.
+pub type PollyFn = Box<Fn(BTreeMap<String, ArgValue>, &Codegen) -> Result<String, String> + Send +
+                       Sync>;
+
+/// A type abstracting the filters used for Polly, such as `upper` in `@name | upper`. A filter is
+/// passed the value of the variable, and the arguments written after the filter's name, and
+/// returns the new value.
+pub type PollyFilter = Box<Fn(Value, &[Value]) -> Result<Value, String> + Send + Sync>;
+
+/// A type abstracting the loaders of locale files that aren't written in Polly. A loader is
+/// passed the name of the locale, and the contents of the file, and converts it into the source
+/// of Polly components, such as `&hello(@name) {Hello @name!}`.
+pub type LocaleLoader = Box<Fn(&str, &str) -> Result<String, String> + Send + Sync>;
+
+/// Called with the errors that are ignored while rendering a template that isn't strict, such as
+/// undefined variables.
+pub type WarningFn = Box<Fn(&CodegenError) + Send + Sync>;
+
+/// The prefix of every component loaded from a template's locales.
+pub const LOCALES_PREFIX: &'static str = "locales.";
 
 macro_rules! template_try {
     ($result:expr) => {
@@ -30,13 +59,20 @@ macro_rules! template_try {
 /// ## std.each
 /// **Arguments**
 /// 
-/// - array - The array of JSON to be iterated over.
+/// - array - The array, or object of JSON to be iterated over.
 /// - component - The component to render for each element in array.
+/// - else(*optional*) - The component rendered instead, if the array is empty, or null.
+/// - separator(*optional*) - The component rendered in between each element.
 /// 
 /// Applies a component to each element in the array. If the component takes more than one argument,
 /// the component can only be used with objects(in which case the function will attempt to find the
 /// object's property based on the argument name), or arrays of the same length as the number of
-/// arguments.
+/// arguments. When iterating over an object, a component taking two arguments is passed each key,
+/// and value.
+///
+/// The component also has a `@loop` variable, holding the `index` of the element, its `index1`
+/// counting from one, whether it's the `first`, or `last` element, the `length` of the array, and
+/// the `key` of the element when iterating over an object.
 /// 
 /// ```
 /// extern crate serde_json;
@@ -158,79 +194,235 @@ macro_rules! template_try {
 ///      assert_eq!(template.json(json).no_locales().render("en").unwrap(), EXPECTED);
 /// }
 /// ```
+///
+/// ## std.eq, std.ne, std.lt, std.le, std.gt, std.ge
+/// **Arguments**
+///
+/// - left - The value on the left of the comparison.
+/// - right - The value on the right of the comparison.
+/// - component - The component rendered if the comparison is true.
+/// - else(*optional*) - The component rendered if the comparison is false.
+/// - json(*optional*) - The JSON passed to the component, or the else component if needed.
+///
+/// Compare `left` to `right`, as equal, not equal, less than, less than or equal, greater than, or
+/// greater than or equal respectively, and render the component if the comparison holds, passing
+/// in the JSON the same way as **std.if**. Numbers are equal if they have the same value, no matter
+/// how they're written, and only numbers, or strings can be ordered.
+///
+/// ## std.in
+/// **Arguments**
+///
+/// Takes the same arguments as **std.eq**, rendering the component if `left` is an element of the
+/// array `right`, a key of the object `right`, or part of the string `right`.
+///
+/// ## std.match
+/// **Arguments**
+///
+/// - value - The value to match.
+/// - else(*optional*) - The component rendered if no case matches.
+/// - json(*optional*) - The JSON passed to the component that's rendered if needed.
+///
+/// Every other argument is a case, named after the value it matches, and renders its component if
+/// `value` written out as text is the same as the case's name.
+///
+/// ```
+/// extern crate serde_json;
+/// extern crate polly;
+///
+/// use serde_json::Value;
+/// use polly::Template;
+/// fn main() {
+///     let json: Value = serde_json::from_str(r#"{"status": "pending"}"#).unwrap();
+///     let json = json.as_object().unwrap().clone();
+///     let template = Template::load_from_source("documentation", r#"
+///     &done {Done}
+///     &waiting {Waiting}
+///     &unknown {Unknown}
+///     /p {$std.match(value = @status, done = &done, pending = &waiting, else = &unknown)}
+///     "#);
+///
+///      assert_eq!(template.json(json).no_locales().render("en").unwrap(), "<p>Waiting</p>");
+/// }
+/// ```
+///
+/// ## std.number, std.percent
+/// **Arguments**
+///
+/// - value - The number, or a string containing one.
+/// - decimals(*optional*) - The number of digits after the decimal point.
+///
+/// Write the number the way the locale being rendered does, with its decimal point, and its
+/// separator between groups of digits. Without `decimals` numbers have up to three digits after
+/// the decimal point, and percentages none. **std.percent** writes `0.25` as 25%.
+///
+/// ## std.currency
+/// **Arguments**
+///
+/// - value - The amount, or a string containing it.
+/// - currency - The ISO 4217 code of the currency, such as "EUR".
+/// - decimals(*optional*) - The number of digits after the decimal point, otherwise the number the
+/// currency uses.
+///
+/// Writes the amount with the symbol of the currency, placed where the locale places it.
+///
+/// ## std.date
+/// **Arguments**
+///
+/// - value - An ISO 8601 date, such as "2016-03-04", or a Unix timestamp in seconds.
+/// - style(*optional*) - "short", "medium", "long", or "full". "medium" by default.
+/// - pattern(*optional*) - A CLDR date pattern, such as "EEEE d MMMM y", used instead of the
+/// locale's pattern for the style.
+///
+/// Writes the date in the language, and order of the locale. Only the date is written, any time
+/// in the value is ignored.
+///
+/// ```
+/// extern crate serde_json;
+/// extern crate polly;
+///
+/// use serde_json::Value;
+/// use polly::Template;
+/// fn main() {
+///     let json = r#"{"price": 1234.5, "day": "2016-03-04"}"#;
+///     let json: Value = serde_json::from_str(json).unwrap();
+///     let json = json.as_object().unwrap().clone();
+///     let template = Template::load_from_source("documentation", r#"
+///     /p {$std.currency(value = @price, currency = "EUR")}
+///     /p {$std.date(value = @day, style = "long")}
+///     "#);
+///
+///      assert_eq!(template.json(json).no_locales().render("de").unwrap(),
+///                 "<p>1.234,50\u{a0}€</p><p>4. März 2016</p>");
+/// }
+/// ```
 pub fn std_functions() -> HashMap<String, PollyFn> {
     use serde_json::Value;
     use compiler::tokens::ArgValue::*;
 
     let mut map: HashMap<String, PollyFn> = HashMap::new();
 
-    map.insert(String::from("std.each"), Box::new(|args, parent| {
+    map.insert(String::from("std.each"), Box::new(|args, codegen| {
+        let component = match args.get("component") {
+            Some(&Comp(Some(ref component))) => component,
+            _ => return Err(String::from("type passed in wasn't a component")),
+        };
+        let items: Vec<(Option<&String>, &Value)> = match args.get("array") {
+            Some(&Json(Some(Value::Array(ref array)))) => {
+                array.iter().map(|item| (None, item)).collect()
+            }
+            Some(&Json(Some(Value::Object(ref object)))) => {
+                object.iter().map(|(key, value)| (Some(key), value)).collect()
+            }
+            Some(&Json(Some(Value::Null))) => Vec::new(),
+            array => {
+                return Err(format!("type passed in wasn't an array, or object it was: {:#?}",
+                                   array))
+            }
+        };
+
+        if items.is_empty() {
+            return match args.get("else") {
+                Some(&Comp(Some(ref component))) => {
+                    template_try!(codegen.call_component(component, BTreeMap::new()))
+                }
+                Some(other) => {
+                    Err(format!("The else arg, wasn't a component it was a {:#?}", other))
+                }
+                None => Ok(String::new()),
+            };
+        }
+
+        let separator = match args.get("separator") {
+            Some(&Comp(Some(ref separator))) => Some(separator),
+            Some(other) => {
+                return Err(format!("The separator arg, wasn't a component it was a {:#?}", other))
+            }
+            None => None,
+        };
+
+        let length = items.len();
         let mut output = String::new();
-        if let Some(&Json(Some(Value::Array(ref array)))) = args.get("array") {
-            if let Some(&Comp(Some(ref component))) = args.get("component") {
-                match component.number_of_args() {
-                    0 => {
-                        for _ in array {
-                            match Template::call_component(&component, parent) {
-                                Ok(html) => output.push_str(&*html),
-                                Err(error) => return Err(format!("{:#?}", error)),
+        for (index, (key, item)) in items.into_iter().enumerate() {
+            if let Some(separator) = separator {
+                if index > 0 {
+                    match codegen.call_component(separator, BTreeMap::new()) {
+                        Ok(html) => output.push_str(&*html),
+                        Err(error) => return Err(format!("{:#?}", error)),
+                    }
+                }
+            }
+
+            let mut variables = match each_variables(component, key, item) {
+                Ok(variables) => variables,
+                Err(error) => return Err(error),
+            };
+            let mut meta = BTreeMap::new();
+            meta.insert(String::from("index"), Value::U64(index as u64));
+            meta.insert(String::from("index1"), Value::U64(index as u64 + 1));
+            meta.insert(String::from("first"), Value::Bool(index == 0));
+            meta.insert(String::from("last"), Value::Bool(index + 1 == length));
+            meta.insert(String::from("length"), Value::U64(length as u64));
+            if let Some(key) = key {
+                meta.insert(String::from("key"), Value::String(key.clone()));
+            }
+            variables.entry(String::from("loop")).or_insert(Value::Object(meta));
+
+            match codegen.call_component(component, variables) {
+                Ok(html) => output.push_str(&*html),
+                Err(error) => return Err(format!("{:#?}", error)),
+            }
+        }
+        Ok(output)
+    }));
+
+    /// Gets the variables passed to the component of `std.each` for a single item. When an
+    /// object is iterated over, a component taking two arguments is passed the key, and value.
+    fn each_variables(component: &Component,
+                      key: Option<&String>,
+                      item: &Value)
+                      -> Result<BTreeMap<String, Value>, String> {
+        let names: Vec<String> = component.args().iter().map(|arg| arg.value()).collect();
+        let mut variables = BTreeMap::new();
+
+        match (names.len(), key) {
+            (0, _) => {}
+            (1, _) => {
+                variables.insert(names[0].clone(), item.clone());
+            }
+            (2, Some(key)) => {
+                variables.insert(names[0].clone(), Value::String(key.clone()));
+                variables.insert(names[1].clone(), item.clone());
+            }
+            _ => {
+                match *item {
+                    Value::Object(ref object) => {
+                        for name in names {
+                            if let Some(value) = object.get(&name) {
+                                variables.insert(name, value.clone());
                             }
                         }
-                        Ok(output)
                     }
-                    1 => {
-                        let name = component.args().first().unwrap().value();
-                        for item in array {
-                            let mut map = BTreeMap::new();
-                            map.insert(name.clone(), item.clone());
-                            match Template::call_component_with_args(&component, parent, map) {
-                                Ok(html) => output.push_str(&*html),
-                                Err(error) => return Err(format!("{:#?}", error)),
-                            }
+                    Value::Array(ref array) if array.len() == names.len() => {
+                        for (name, value) in names.into_iter().zip(array) {
+                            variables.insert(name, value.clone());
                         }
-                        Ok(output)
                     }
                     _ => {
-                        if let Some(&Value::Object(_)) = array.first() {
-                            let iter = array.iter();
-                            for json in iter {
-                                if let Value::Object(ref object) = *json {
-                                    let mut map = BTreeMap::new();
-                                    for key in component.args() {
-                                        let key = key.value();
-                                        if let Some(value) = object.get(&*key) {
-                                            map.insert(key, value.clone());
-                                        }
-                                    }
-                                    match Template::call_component_with_args(&component, parent, map) {
-                                        Ok(html) => output.push_str(&*html),
-                                        Err(error) => return Err(format!("{:#?}", error)),
-                                    }
-                                }
-                            }
-                            Ok(output)
-                        } else {
-                            Err(String::from("JSON wasn't an object, and the component has \
-                                              multiple arguments, so it can't be properly \
-                                              destructured."))
-                        }
-                    } 
+                        return Err(String::from("JSON wasn't an object, or an array with a value \
+                                                 for each of the component's arguments, so it \
+                                                 can't be properly destructured."))
+                    }
                 }
-            } else {
-                Err(String::from("type passed in wasn't a component"))
             }
-
-        } else {
-            Err(format!("type passed in wasn't an array it was: {:#?}",
-                        args.get("array")))
         }
-    }));
+        Ok(variables)
+    }
 
     map.insert(String::from("std.if"),
-               Box::new(|args, parent| {
+               Box::new(|args, codegen| {
                    if let Some(&Json(Some(ref json))) = args.get("condition") {
                        if json_into_bool(json) {
-                           eval_conditional(&args, &parent, "component")
+                           eval_conditional(&args, codegen, "component")
                        } else {
                            Ok(String::new())
                        }
@@ -241,12 +433,12 @@ pub fn std_functions() -> HashMap<String, PollyFn> {
                }));
 
     map.insert(String::from("std.if_else"),
-               Box::new(|args, parent| {
+               Box::new(|args, codegen| {
                    if let Some(&Json(Some(ref json))) = args.get("condition") {
                        if json_into_bool(json) {
-                           eval_conditional(&args, &parent, "component")
+                           eval_conditional(&args, codegen, "component")
                        } else {
-                           eval_conditional(&args, &parent, "else")
+                           eval_conditional(&args, codegen, "else")
                        }
                    } else {
                        Err(format!("The json arg, wasn't JSON it is {:#?}",
@@ -255,14 +447,219 @@ pub fn std_functions() -> HashMap<String, PollyFn> {
 
                }));
 
+    map.insert(String::from("std.eq"),
+               Box::new(|args, codegen| eval_comparison(&args, codegen, &|left, right| {
+                   Ok(json_eq(left, right))
+               })));
+    map.insert(String::from("std.ne"),
+               Box::new(|args, codegen| eval_comparison(&args, codegen, &|left, right| {
+                   Ok(!json_eq(left, right))
+               })));
+    map.insert(String::from("std.lt"),
+               Box::new(|args, codegen| eval_comparison(&args, codegen, &|left, right| {
+                   json_cmp(left, right).map(|ordering| ordering == Ordering::Less)
+               })));
+    map.insert(String::from("std.le"),
+               Box::new(|args, codegen| eval_comparison(&args, codegen, &|left, right| {
+                   json_cmp(left, right).map(|ordering| ordering != Ordering::Greater)
+               })));
+    map.insert(String::from("std.gt"),
+               Box::new(|args, codegen| eval_comparison(&args, codegen, &|left, right| {
+                   json_cmp(left, right).map(|ordering| ordering == Ordering::Greater)
+               })));
+    map.insert(String::from("std.ge"),
+               Box::new(|args, codegen| eval_comparison(&args, codegen, &|left, right| {
+                   json_cmp(left, right).map(|ordering| ordering != Ordering::Less)
+               })));
+
+    map.insert(String::from("std.in"),
+               Box::new(|args, codegen| {
+                   eval_comparison(&args, codegen, &|left, right| {
+                       match *right {
+                           Value::Array(ref array) => {
+                               Ok(array.iter().any(|value| json_eq(left, value)))
+                           }
+                           Value::Object(ref object) => {
+                               Ok(object.contains_key(&value_to_string(left)))
+                           }
+                           Value::String(ref string) => {
+                               Ok(string.contains(&*value_to_string(left)))
+                           }
+                           Value::Null => Ok(false),
+                           ref right => {
+                               Err(format!("The right arg, wasn't an array, object, or string it \
+                                            is {:#?}",
+                                           right))
+                           }
+                       }
+                   })
+               }));
+
+    map.insert(String::from("std.match"),
+               Box::new(|args, codegen| {
+                   let value = match args.get("value") {
+                       Some(&Json(Some(ref value))) => value_to_string(value),
+                       value => {
+                           return Err(format!("The value arg, wasn't JSON it is {:#?}", value))
+                       }
+                   };
+
+                   let case = args.keys().find(|name| {
+                       !["value", "json", "else"].contains(&name.as_str()) && **name == value
+                   });
+                   match case {
+                       Some(case) => eval_conditional(&args, codegen, case),
+                       None if args.contains_key("else") => {
+                           eval_conditional(&args, codegen, "else")
+                       }
+                       None => Ok(String::new()),
+                   }
+               }));
+
+    map.insert(String::from("std.number"),
+               Box::new(|args, codegen| {
+                   let value = match number_arg(&args, "value") {
+                       Ok(value) => value,
+                       Err(error) => return Err(error),
+                   };
+                   let decimals = match decimals_arg(&args) {
+                       Ok(decimals) => decimals,
+                       Err(error) => return Err(error),
+                   };
+                   let text = match decimals {
+                       Some(decimals) => format::number(codegen.lang(), value, decimals, decimals),
+                       None => format::number(codegen.lang(), value, 0, 3),
+                   };
+                   Ok(escape_html(&text))
+               }));
+
+    map.insert(String::from("std.percent"),
+               Box::new(|args, codegen| {
+                   let value = match number_arg(&args, "value") {
+                       Ok(value) => value,
+                       Err(error) => return Err(error),
+                   };
+                   let decimals = match decimals_arg(&args) {
+                       Ok(decimals) => decimals,
+                       Err(error) => return Err(error),
+                   };
+                   let decimals = decimals.unwrap_or(0);
+                   Ok(escape_html(&format::percent(codegen.lang(), value, decimals)))
+               }));
+
+    map.insert(String::from("std.currency"),
+               Box::new(|args, codegen| {
+                   let value = match number_arg(&args, "value") {
+                       Ok(value) => value,
+                       Err(error) => return Err(error),
+                   };
+                   let code = match args.get("currency") {
+                       Some(&Json(Some(Value::String(ref code)))) => code,
+                       code => {
+                           return Err(format!("The currency arg, wasn't a string it is {:#?}",
+                                              code))
+                       }
+                   };
+                   let decimals = match decimals_arg(&args) {
+                       Ok(decimals) => decimals,
+                       Err(error) => return Err(error),
+                   };
+                   Ok(escape_html(&format::currency(codegen.lang(), value, code, decimals)))
+               }));
+
+    map.insert(String::from("std.date"),
+               Box::new(|args, codegen| {
+                   let value = match args.get("value") {
+                       Some(&Json(Some(ref value))) => value,
+                       value => {
+                           return Err(format!("The value arg, wasn't JSON it is {:#?}", value))
+                       }
+                   };
+                   let pattern = match (args.get("pattern"), args.get("style")) {
+                       (Some(&Json(Some(Value::String(ref pattern)))), _) => &**pattern,
+                       (Some(pattern), _) => {
+                           return Err(format!("The pattern arg, wasn't a string it is {:#?}",
+                                              pattern))
+                       }
+                       (None, Some(&Json(Some(Value::String(ref style))))) => {
+                           match format::date_pattern(codegen.lang(), style) {
+                               Some(pattern) => pattern,
+                               None => {
+                                   return Err(format!("The style arg, wasn't short, medium, \
+                                                       long, or full it is {:?}",
+                                                      style))
+                               }
+                           }
+                       }
+                       (None, Some(style)) => {
+                           return Err(format!("The style arg, wasn't a string it is {:#?}", style))
+                       }
+                       (None, None) => format::date_pattern(codegen.lang(), "medium").unwrap(),
+                   };
+                   format::date(codegen.lang(), value, pattern).map(|date| escape_html(&date))
+               }));
+
+    /// A number, or a string containing one.
+    fn number_arg(args: &BTreeMap<String, ArgValue>, name: &str) -> Result<f64, String> {
+        match args.get(name) {
+            Some(&Json(Some(ref value))) => {
+                match format::to_number(value) {
+                    Some(number) => Ok(number),
+                    None => Err(format!("The {} arg, wasn't a number it is {:#?}", name, value)),
+                }
+            }
+            value => Err(format!("The {} arg, wasn't JSON it is {:#?}", name, value)),
+        }
+    }
+
+    /// The number of digits after the decimal point, if it was passed.
+    fn decimals_arg(args: &BTreeMap<String, ArgValue>) -> Result<Option<usize>, String> {
+        match args.get("decimals") {
+            Some(&Json(Some(Value::U64(decimals)))) if decimals <= 20 => {
+                Ok(Some(decimals as usize))
+            }
+            Some(&Json(Some(Value::I64(decimals)))) if decimals >= 0 && decimals <= 20 => {
+                Ok(Some(decimals as usize))
+            }
+            None => Ok(None),
+            decimals => {
+                Err(format!("The decimals arg, wasn't a number from 0 to 20 it is {:#?}",
+                            decimals))
+            }
+        }
+    }
+
+    /// Renders the component if `test` passes for the left, and right arguments, otherwise the
+    /// else component if there is one.
+    fn eval_comparison(args: &BTreeMap<String, ArgValue>,
+                       codegen: &Codegen,
+                       test: &Fn(&Value, &Value) -> Result<bool, String>)
+                       -> Result<String, String> {
+        let left = match args.get("left") {
+            Some(&Json(Some(ref left))) => left,
+            left => return Err(format!("The left arg, wasn't JSON it is {:#?}", left)),
+        };
+        let right = match args.get("right") {
+            Some(&Json(Some(ref right))) => right,
+            right => return Err(format!("The right arg, wasn't JSON it is {:#?}", right)),
+        };
+
+        match test(left, right) {
+            Ok(true) => eval_conditional(args, codegen, "component"),
+            Ok(false) if args.contains_key("else") => eval_conditional(args, codegen, "else"),
+            Ok(false) => Ok(String::new()),
+            Err(error) => Err(error),
+        }
+    }
+
     fn eval_conditional(args: &BTreeMap<String, ArgValue>,
-                        parent: &Rc<RefCell<Template>>,
+                        codegen: &Codegen,
                         component_name: &str)
                         -> Result<String, String> {
         if let Some(&Comp(Some(ref component))) = args.get(component_name) {
             if let Some(&Json(Some(ref json))) = args.get("json") {
                 match component.number_of_args() {
-                    0 => template_try!(Template::call_component(component, parent)),
+                    0 => template_try!(codegen.call_component(component, BTreeMap::new())),
                     1 => {
                         let name = component.args().first().unwrap().value();
                         let mut map = BTreeMap::new();
@@ -277,13 +674,11 @@ pub fn std_functions() -> HashMap<String, PollyFn> {
                             }
 
                         }
-                        template_try!(Template::call_component_with_args(component, parent, map));
+                        template_try!(codegen.call_component(component, map));
                     }
                     _ => {
                         if let Value::Object(ref map) = *json {
-                            template_try!(Template::call_component_with_args(component,
-                                                                             parent,
-                                                                             map.clone()));
+                            template_try!(codegen.call_component(component, map.clone()));
                         } else {
                             Err(String::from("Component has more than one argument, and the JSON \
                                               passed in wasn't an object, so I don't know how to \
@@ -292,7 +687,7 @@ pub fn std_functions() -> HashMap<String, PollyFn> {
                     }
                 }
             } else {
-                template_try!(Template::call_component(component, parent))
+                template_try!(codegen.call_component(component, BTreeMap::new()))
             }
         } else {
             Err(format!("The component arg, wasn't a component it was a {:#?}",
@@ -302,13 +697,167 @@ pub fn std_functions() -> HashMap<String, PollyFn> {
     map
 }
 
+/// # Standard Filters of Polly
+/// **Note:** Like `std_functions`, this is only exposed as a way to incorporate Polly's filters
+/// into the documentation.
+///
+/// - upper - The value in uppercase.
+/// - lower - The value in lowercase.
+/// - trim - The value without leading, or trailing whitespace.
+/// - truncate(length) - The first `length` characters of the value, followed by "..." if any
+///   were cut off.
+/// - default(value) - `value` if the variable is null, an empty string, or isn't defined at all.
+/// - join(separator) - The elements of an array, separated by `separator`.
+/// - length - The number of elements in an array, or object, or characters in a string.
+/// - json - The value written as JSON.
+/// - urlencode - The value percent encoded, so it can be placed in a URL.
+///
+/// Filters only apply to variables, so to filter a variable in an attribute the value is written
+/// without quotes, as quoted values are used as is.
+///
+/// ```
+/// extern crate serde_json;
+/// extern crate polly;
+///
+/// use serde_json::Value;
+/// use polly::Template;
+/// fn main() {
+///     let json = r#"{"title": "Rust templates made easy", "query": "rust & polly"}"#;
+///     let json: Value = serde_json::from_str(json).unwrap();
+///     let json = json.as_object().unwrap().clone();
+///     let template = Template::load_from_source("documentation", r#"
+///     /p{@title | truncate(10) | upper}
+///     /a(href=@query | urlencode){Search}
+///     "#);
+///
+///      assert_eq!(template.json(json).no_locales().render("en").unwrap(),
+///                 "<p>RUST TEMPL...</p><a href=\"rust%20%26%20polly\">Search</a>");
+/// }
+/// ```
+pub fn std_filters() -> HashMap<String, PollyFilter> {
+    let mut map: HashMap<String, PollyFilter> = HashMap::new();
+
+    map.insert(String::from("upper"),
+               Box::new(|value, _| Ok(Value::String(value_to_string(&value).to_uppercase()))));
+    map.insert(String::from("lower"),
+               Box::new(|value, _| Ok(Value::String(value_to_string(&value).to_lowercase()))));
+    map.insert(String::from("trim"),
+               Box::new(|value, _| Ok(Value::String(value_to_string(&value).trim().to_owned()))));
+
+    map.insert(String::from("truncate"), Box::new(|value, args| {
+        let length = match args.first() {
+            Some(&Value::U64(length)) => length as usize,
+            Some(&Value::I64(length)) if length >= 0 => length as usize,
+            _ => return Err(String::from("truncate takes the number of characters to keep")),
+        };
+        let string = value_to_string(&value);
+        if string.chars().count() > length {
+            Ok(Value::String(string.chars().take(length).collect::<String>() + "..."))
+        } else {
+            Ok(Value::String(string))
+        }
+    }));
+
+    map.insert(String::from("default"), Box::new(|value, args| {
+        let fallback = match args.first() {
+            Some(fallback) => fallback,
+            None => return Err(String::from("default takes the value to use instead")),
+        };
+        match value {
+            Value::Null => Ok(fallback.clone()),
+            Value::String(ref string) if string.is_empty() => Ok(fallback.clone()),
+            value => Ok(value),
+        }
+    }));
+
+    map.insert(String::from("join"), Box::new(|value, args| {
+        let separator = match args.first() {
+            Some(separator) => value_to_string(separator),
+            None => String::new(),
+        };
+        match value {
+            Value::Array(ref array) => {
+                let strings: Vec<String> = array.iter().map(value_to_string).collect();
+                Ok(Value::String(strings.join(&separator)))
+            }
+            Value::Null => Ok(Value::Null),
+            _ => Err(String::from("join can only be used on arrays")),
+        }
+    }));
+
+    map.insert(String::from("length"), Box::new(|value, _| {
+        match value {
+            Value::Array(ref array) => Ok(Value::U64(array.len() as u64)),
+            Value::Object(ref object) => Ok(Value::U64(object.len() as u64)),
+            Value::String(ref string) => Ok(Value::U64(string.chars().count() as u64)),
+            Value::Null => Ok(Value::U64(0)),
+            _ => Err(String::from("length can only be used on arrays, objects, and strings")),
+        }
+    }));
+
+    map.insert(String::from("json"), Box::new(|value, _| {
+        match serde_json::to_string(&value) {
+            Ok(json) => Ok(Value::String(json)),
+            Err(error) => Err(error.to_string()),
+        }
+    }));
+
+    map.insert(String::from("urlencode"), Box::new(|value, _| {
+        let mut encoded = String::new();
+        for byte in value_to_string(&value).bytes() {
+            if byte < 128 && (byte as char).is_alphanumeric() || b"-_.~".contains(&byte) {
+                encoded.push(byte as char);
+            } else {
+                encoded.push_str(&format!("%{:02X}", byte));
+            }
+        }
+        Ok(Value::String(encoded))
+    }));
+
+    map
+}
+
+/// # Standard Locale Loaders of Polly
+/// **Note:** Like `std_functions`, this is only exposed as a way to incorporate Polly's loaders
+/// into the documentation.
+///
+/// Loaders are keyed by the extension of the files they read. Alongside the Polly file with the
+/// same name as the template, each locale can have a catalog with the same name, and one of
+/// these extensions, such as `locales/de/index.ftl` for `index.polly`.
+///
+/// - ftl - Fluent files. Messages, attributes, variables, terms, and select expressions are
+///   supported.
+/// - po - gettext PO files. Entries are named by their `msgctxt`, or `msgid`, and variables are
+///   written as `{name}`.
+/// - json - A flat JSON object of messages, with variables written as `{name}`, and objects of
+///   messages being plurals.
+///
+/// Every message becomes a component taking each variable it uses as an argument, in the order
+/// they first appear, so they're best called with named arguments:
+/// `&locales.items(count = @cart.length)`.
+pub fn std_loaders() -> HashMap<String, LocaleLoader> {
+    let mut map: HashMap<String, LocaleLoader> = HashMap::new();
+    map.insert(String::from("ftl"), Box::new(catalog::fluent));
+    map.insert(String::from("po"), Box::new(catalog::gettext));
+    map.insert(String::from("json"), Box::new(catalog::json));
+    map
+}
+
 /// The Polly template.
 pub struct Template {
     components: HashMap<String, Component>,
+    default_locale: Option<String>,
     file: PathBuf,
+    filters: HashMap<String, PollyFilter>,
     functions: HashMap<String, PollyFn>,
+    html_comments: bool,
+    loaders: HashMap<String, LocaleLoader>,
+    on_warning: Option<WarningFn>,
     source: String,
     locales_dir: Option<String>,
+    locale_fallback: bool,
+    strict: bool,
+    undefined_filters: HashSet<String>,
     variables: BTreeMap<String, Value>,
 }
 
@@ -333,20 +882,20 @@ impl Template {
         }
     }
 
-    fn call_component(component: &Component, parent: &Rc<RefCell<Template>>) -> CodegenResult {
-        Codegen::call_component(component, None, parent)
+    /// Get a component from within the template.
+    pub fn get_component(&self, name: &str) -> Option<&Component> {
+        self.components.get(name)
     }
 
-    fn call_component_with_args(component: &Component,
-                                parent: &Rc<RefCell<Template>>,
-                                map: BTreeMap<String, Value>)
-                                -> CodegenResult {
-        Codegen::call_component(component, Some(map), parent)
+    /// Get a filter from within the template.
+    pub fn get_filter(&self, name: &str) -> Option<&PollyFilter> {
+        self.filters.get(name)
     }
 
-    /// Get a component from within the template.
-    pub fn get_component(&self, name: &str) -> Option<&Component> {
-        self.components.get(name)
+    /// Whether the filter is passed `null` for a variable that isn't defined, rather than that
+    /// being an error in a strict template.
+    pub fn handles_undefined(&self, filter: &str) -> bool {
+        self.undefined_filters.contains(filter)
     }
 
     /// Get a function from within the template.
@@ -367,14 +916,42 @@ impl Template {
     fn new<P: AsRef<Path>, S: Into<String>>(path: P, source: S) -> Self {
         Template {
             components: HashMap::new(),
+            default_locale: None,
             file: path.as_ref().to_path_buf(),
+            filters: std_filters(),
             functions: std_functions(),
+            html_comments: false,
+            loaders: std_loaders(),
+            on_warning: None,
             source: source.into(),
             locales_dir: Some(String::from("./templates/locales")),
+            undefined_filters: vec![String::from("default")].into_iter().collect(),
+            locale_fallback: true,
+            strict: false,
             variables: BTreeMap::new(),
         }
     }
 
+    /// Whether comments in the template are rendered as HTML comments. They're left out of the
+    /// HTML by default.
+    pub fn html_comments(mut self, html_comments: bool) -> Self {
+        self.html_comments = html_comments;
+        self
+    }
+
+    /// Whether undefined variables, and missing locale components are errors. By default they
+    /// render as nothing, and are only passed to the warning callback.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Sets the callback passed the errors that are ignored when the template isn't strict.
+    pub fn on_warning(mut self, on_warning: WarningFn) -> Self {
+        self.on_warning = Some(on_warning);
+        self
+    }
+
     /// Specify that a template has no locales available.
     pub fn no_locales(mut self) -> Self {
         self.locales_dir = None;
@@ -399,6 +976,21 @@ impl Template {
         self
     }
 
+    /// The locale used when neither the locale being rendered, nor any of its fallbacks, has the
+    /// template, or one of the components it calls.
+    pub fn default_locale<S: Into<String>>(mut self, default_locale: S) -> Self {
+        self.default_locale = Some(default_locale.into());
+        self
+    }
+
+    /// Whether a locale falls back to more general locales, such as `de-AT` to `de`, and then to
+    /// the default locale, when it doesn't have the template, or one of its components. This is
+    /// on by default, without it only the exact locale is used.
+    pub fn locale_fallback(mut self, locale_fallback: bool) -> Self {
+        self.locale_fallback = locale_fallback;
+        self
+    }
+
     /// Registers a function to the template.
     pub fn register(&mut self, name: String, function: PollyFn) -> Result<(), TemplateError> {
         if let Some(_) = self.functions.insert(name, function) {
@@ -408,11 +1000,51 @@ impl Template {
         }
     }
 
+    /// Registers a filter to the template.
+    pub fn register_filter(&mut self,
+                           name: String,
+                           filter: PollyFilter)
+                           -> Result<(), TemplateError> {
+        if let Some(_) = self.filters.insert(name, filter) {
+            Err(TemplateError::PreDefinedFilter)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Registers a filter to the template, that is also passed `null` for a variable that isn't
+    /// defined, rather than that being an error in a strict template, like `default`.
+    pub fn register_undefined_filter(&mut self,
+                                     name: String,
+                                     filter: PollyFilter)
+                                     -> Result<(), TemplateError> {
+        match self.register_filter(name.clone(), filter) {
+            Ok(()) => {
+                let _ = self.undefined_filters.insert(name);
+                Ok(())
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Registers a loader for locale files with the extension `extension`.
+    pub fn register_loader(&mut self,
+                           extension: String,
+                           loader: LocaleLoader)
+                           -> Result<(), TemplateError> {
+        if let Some(_) = self.loaders.insert(extension, loader) {
+            Err(TemplateError::PreDefinedLoader)
+        } else {
+            Ok(())
+        }
+    }
+
     /// Imports components from another template.
     pub fn import<P: AsRef<Path>>(&mut self, path: P) -> Result<(), TemplateError> {
-        match Template::read_to_source(path) {
+        match Template::read_to_source(&path) {
             Ok(source) => {
-                for (key, value) in Parser::component_pass(Lexer::new(&*source).output()) {
+                let lexemes = Lexer::with_file(&*source, path).output();
+                for (key, value) in Parser::component_pass(lexemes) {
                     if let Err(error) = self.add_component(key, value) {
                         return Err(error);
                     };
@@ -424,7 +1056,10 @@ impl Template {
     }
 
     fn read_to_source<P: AsRef<Path>>(path: P) -> Result<String, TemplateError> {
-        let mut file = File::open(path.as_ref()).unwrap();
+        let mut file = match File::open(path.as_ref()) {
+            Ok(file) => file,
+            Err(error) => return Err(TemplateError::IoError(error)),
+        };
         let mut contents = String::new();
         match file.read_to_string(&mut contents) {
             Ok(_) => Ok(contents),
@@ -432,128 +1067,582 @@ impl Template {
         }
     }
 
-    /// Renders the template into a HTML String.
-    pub fn render(mut self, lang: &str) -> Result<String, TemplateError> {
-        let output = {
-            let parser = Parser::new(Lexer::new(&self.source).output());
+    /// Parses the template, and the components of every locale available, producing a
+    /// `CompiledTemplate` which can be rendered any number of times. Compiling is all or nothing:
+    /// a locale file that can't be read, or a catalog that can't be loaded is an error for the
+    /// whole template, rather than only its locale. Parse errors don't fail compiling, they're
+    /// found with `CompiledTemplate::validate`.
+    pub fn compile(mut self) -> Result<CompiledTemplate, TemplateError> {
+        let ast = {
+            let parser = Parser::new(Lexer::with_file(&self.source, &self.file).output());
             if let Err(error) = self.add_components(parser.get_components()) {
                 return Err(error);
             };
             parser.output()
         };
-        let file_name = self.file.file_name().unwrap().to_str().unwrap().to_owned();
-
-        let locales_dir = match self.locales_dir {
-            Some(ref locales_dir) => locales_dir.clone(),
-            None => String::new(),
-        };
 
-        if !locales_dir.is_empty() {
-            let path = format!("{dir}/{lang}/{file}",
-                               dir = locales_dir,
-                               lang = lang,
-                               file = file_name);
-            match Template::read_to_source(path) {
-                Ok(source) => {
-                    for (key, value) in Parser::component_pass(Lexer::new(&*source).output()) {
-                        let new_key = format!("locales.{}", key);
-                        if let Err(error) = self.add_component(new_key, value) {
-                            return Err(error);
-                        };
-                    }
+        let locales = match self.locales_dir {
+            Some(ref locales_dir) => {
+                match self.load_locales(locales_dir) {
+                    Ok(locales) => Some(locales),
+                    Err(error) => return Err(error),
                 }
-                error => return error,
             }
-        }
-
-        let variables = self.variables.to_owned();
+            None => None,
+        };
 
-        let mut codegen = Codegen::new(output, variables, Rc::new(RefCell::new(self)));
-        match codegen.generate_html() {
-            Ok(html) => Ok(html),
-            Err(error) => Err(TemplateError::CodegenError(error)),
-        }
-    }
-    /// Renders the component, or panics if there is an error.
-    pub fn unwrap_render(self, locale: &str) -> String {
-        let file_name = self.file.file_name().unwrap().to_str().unwrap().to_owned();
-        let source = self.source.clone();
-        match self.render(locale) {
-            Ok(html) => html,
-            Err(error) => {
-                Template::render_error(error, source, file_name);
-                panic!()
-            }
-        }
+        Ok(CompiledTemplate {
+            ast: ast,
+            components: share_components(self.components),
+            default_locale: self.default_locale,
+            file: self.file,
+            filters: self.filters,
+            functions: self.functions,
+            html_comments: self.html_comments,
+            locales: locales,
+            locale_fallback: self.locale_fallback,
+            on_warning: self.on_warning,
+            source: self.source,
+            strict: self.strict,
+            undefined_filters: self.undefined_filters,
+        })
     }
 
-    fn render_error(error: TemplateError, source: String, file_name: String) {
-        if let TemplateError::CodegenError(CodegenError::AstError(error)) = error {
-            const SPACE: usize = 1;
-            const CONSOLE_WIDTH: usize = 89;
-            if error == AstError::Eof {
-                return;
+    /// Reads the components of every locale in the locales directory, that has a file with the
+    /// same name as the template, or a catalog with the same name as the template, and the
+    /// extension of one of the loaders.
+    fn load_locales(&self,
+                    locales_dir: &str)
+                    -> Result<HashMap<String, HashMap<String, Arc<Component>>>, TemplateError> {
+        let names = (self.file.file_name().and_then(|name| name.to_str()),
+                     self.file.file_stem().and_then(|stem| stem.to_str()));
+        let (file_name, stem) = match names {
+            (Some(file_name), Some(stem)) => (file_name.to_owned(), stem.to_owned()),
+            _ => {
+                let message = format!("`{}` isn't the path of a file with a UTF-8 name, so its \
+                                       locales can't be found",
+                                      self.file.display());
+                return Err(TemplateError::IoError(io::Error::new(io::ErrorKind::InvalidInput,
+                                                                 message)));
             }
-            let (index, token_length) = error.values();
-            let mut line_number: usize = 0;
-            let mut col_number: usize = 1;
+        };
+        let mut extensions: Vec<&String> = self.loaders.keys().collect();
+        extensions.sort();
 
-            for ch in source[..index].chars() {
-                col_number += 1;
-                if ch == '\n' {
-                    line_number += 1;
-                    col_number = 1;
-                }
-            }
+        let entries = match fs::read_dir(locales_dir) {
+            Ok(entries) => entries,
+            Err(error) => return Err(TemplateError::IoError(error)),
+        };
 
-            let mut section = String::new();
-            for ch in source[..index].chars().rev() {
-                if ch == '\n' {
-                    section = section.chars().rev().collect();
-                    break;
-                } else {
-                    section.push(ch);
-                }
+        let mut locales = HashMap::new();
+        for entry in entries {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(error) => return Err(TemplateError::IoError(error)),
+            };
+            let lang = entry.file_name().to_string_lossy().into_owned();
+            let mut files = vec![(entry.path().join(&file_name), None)];
+            for extension in &extensions {
+                let path = entry.path().join(format!("{}.{}", stem, extension));
+                files.push((path, self.loaders.get(*extension)));
             }
 
-            let file_name_print = format!("{}:{}:{}:", file_name, line_number, col_number);
+            let mut components = HashMap::new();
+            let mut found = false;
+            for (path, loader) in files {
+                if !path.is_file() {
+                    continue;
+                }
+                found = true;
+                let source = match Template::read_to_source(&path) {
+                    Ok(source) => source,
+                    Err(error) => return Err(error),
+                };
+                let lexemes = match loader {
+                    Some(loader) => {
+                        match loader(&lang, &source) {
+                            Ok(source) => lex_catalog(&source, &path),
+                            Err(error) => return Err(TemplateError::InvalidCatalog(path, error)),
+                        }
+                    }
+                    None => Lexer::with_file(&*source, &path).output(),
+                };
 
-            for ch in source[index..].chars() {
-                if ch == '\n' || (section.len() + SPACE + file_name_print.len()) == CONSOLE_WIDTH {
-                    break;
-                } else {
-                    section.push(ch);
+                for (name, component) in Parser::component_pass(lexemes) {
+                    if let Some(_) = components.insert(name, component) {
+                        return Err(TemplateError::PreDefinedComponent);
+                    }
                 }
             }
-            println!("");
-            let mut underline = String::from("^");
 
-            for _ in 1..token_length {
-                underline.push('~');
+            if found {
+                locales.insert(lang, share_components(components));
             }
-            println!("{} {}", file_name_print, error);
-            println!("{} {}", file_name_print, section.trim());
-            println!("{0:>1$}",
-                     underline,
-                     SPACE + col_number + file_name_print.len() -
-                     (section.len() - section.trim().len()));
-        } else {
-            println!("{:?}", error);
         }
+        Ok(locales)
     }
-}
-/// Errors relating to the templating rendering.
-#[derive(Debug)]
+
+    /// Renders the template into a HTML String. If the template is going to be rendered more
+    /// than once, `compile` it, and render the `CompiledTemplate` instead.
+    pub fn render(mut self, lang: &str) -> Result<String, TemplateError> {
+        let variables = mem::replace(&mut self.variables, BTreeMap::new());
+
+        match self.compile() {
+            Ok(template) => template.render(lang, &variables),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Renders the template, writing the HTML into `writer` as it is generated.
+    pub fn render_to<W: io::Write>(mut self,
+                                   lang: &str,
+                                   writer: &mut W)
+                                   -> Result<(), TemplateError> {
+        let variables = mem::replace(&mut self.variables, BTreeMap::new());
+
+        match self.compile() {
+            Ok(template) => template.render_to(lang, &variables, writer),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Renders the template, writing the HTML into a `fmt::Write` such as a `String`.
+    pub fn render_fmt<W: fmt::Write>(mut self,
+                                     lang: &str,
+                                     writer: &mut W)
+                                     -> Result<(), TemplateError> {
+        let variables = mem::replace(&mut self.variables, BTreeMap::new());
+
+        match self.compile() {
+            Ok(template) => template.render_fmt(lang, &variables, writer),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Renders the template, or panics with a diagnostic describing the error.
+    pub fn unwrap_render(self, locale: &str) -> String {
+        let renderer = self.renderer(Style::Rich);
+        match self.render(locale) {
+            Ok(html) => html,
+            Err(error) => panic!("\n{}", renderer.render(&Diagnostic::from(&error))),
+        }
+    }
+
+    /// Creates a `Renderer` for the diagnostics of the template, which already has the
+    /// template's source.
+    pub fn renderer(&self, style: Style) -> Renderer {
+        let mut renderer = Renderer::new(style);
+        renderer.add_source(&self.file, &*self.source);
+        renderer
+    }
+}
+/// A parsed template, holding its AST, components, functions, and the components of each of its
+/// locales. It can be rendered any number of times, in any of its locales.
+///
+/// A `CompiledTemplate` is `Send`, and `Sync`, so a server can compile its templates once, and
+/// share them between all of its threads, by placing them in an `Arc`.
+pub struct CompiledTemplate {
+    ast: Vec<AstResult>,
+    components: HashMap<String, Arc<Component>>,
+    default_locale: Option<String>,
+    file: PathBuf,
+    filters: HashMap<String, PollyFilter>,
+    functions: HashMap<String, PollyFn>,
+    html_comments: bool,
+    locales: Option<HashMap<String, HashMap<String, Arc<Component>>>>,
+    locale_fallback: bool,
+    on_warning: Option<WarningFn>,
+    source: String,
+    strict: bool,
+    undefined_filters: HashSet<String>,
+}
+
+impl CompiledTemplate {
+    /// Get a component from within the template. Components starting with "locales." are looked
+    /// up in the components of `lang`, or the first of its fallbacks that has the component.
+    pub fn get_component(&self, name: &str, lang: &str) -> Option<&Arc<Component>> {
+        if name.starts_with(LOCALES_PREFIX) {
+            match self.locales {
+                Some(ref locales) => {
+                    self.locale_chain(lang)
+                        .into_iter()
+                        .filter_map(|locale| locales[locale].get(&name[LOCALES_PREFIX.len()..]))
+                        .next()
+                }
+                None => None,
+            }
+        } else {
+            self.components.get(name)
+        }
+    }
+
+    /// The locales the template has, that `lang` falls back to, in the order they're tried. For
+    /// `de-AT` that's `de-AT`, then `de`, then the default locale, leaving out any the template
+    /// doesn't have. Locales are compared ignoring case, and whether "-", or "_" separates them.
+    pub fn locale_chain(&self, lang: &str) -> Vec<&str> {
+        let locales = match self.locales {
+            Some(ref locales) => locales,
+            None => return Vec::new(),
+        };
+
+        let mut candidates = vec![lang.replace('_', "-")];
+        if self.locale_fallback {
+            let mut tag = candidates[0].clone();
+            while let Some(end) = tag.rfind('-') {
+                tag.truncate(end);
+                // A single letter, such as the "x" of "en-x-private", only makes sense along with
+                // the subtags after it.
+                if tag.rfind('-').map_or(false, |start| tag.len() - start == 2) {
+                    continue;
+                }
+                candidates.push(tag.clone());
+            }
+            if let Some(ref default_locale) = self.default_locale {
+                candidates.push(default_locale.replace('_', "-"));
+            }
+        }
+
+        let mut chain = Vec::new();
+        for candidate in candidates {
+            let found = locales.keys().find(|locale| {
+                locale.replace('_', "-").to_lowercase() == candidate.to_lowercase()
+            });
+            if let Some(locale) = found {
+                if !chain.contains(&&**locale) {
+                    chain.push(&**locale);
+                }
+            }
+        }
+        chain
+    }
+
+    /// Get a filter from within the template.
+    pub fn get_filter(&self, name: &str) -> Option<&PollyFilter> {
+        self.filters.get(name)
+    }
+
+    /// Whether the filter is passed `null` for a variable that isn't defined, rather than that
+    /// being an error in a strict template.
+    pub fn handles_undefined(&self, filter: &str) -> bool {
+        self.undefined_filters.contains(filter)
+    }
+
+    /// Get a function from within the template.
+    pub fn get_function(&self, name: &str) -> Option<&PollyFn> {
+        self.functions.get(name)
+    }
+
+    /// Whether comments in the template are rendered as HTML comments.
+    pub fn html_comments(&self) -> bool {
+        self.html_comments
+    }
+
+    /// Whether undefined variables, and missing locale components are errors.
+    pub fn strict(&self) -> bool {
+        self.strict
+    }
+
+    /// Passes an error that was ignored, because the template isn't strict, to the warning
+    /// callback.
+    pub fn warn(&self, warning: &CodegenError) {
+        if let Some(ref on_warning) = self.on_warning {
+            on_warning(warning);
+        }
+    }
+
+    /// The path of the template's file.
+    pub fn file(&self) -> &Path {
+        &self.file
+    }
+
+    /// The source of the template.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Creates a `Renderer` for the diagnostics of the template, which already has the
+    /// template's source.
+    pub fn renderer(&self, style: Style) -> Renderer {
+        let mut renderer = Renderer::new(style);
+        renderer.add_source(&self.file, &*self.source);
+        renderer
+    }
+
+    /// Every parse error in the template, its components, and the components of its locales,
+    /// ordered by where they are in the source. Rendering stops at the first error it comes
+    /// across, so this is used to report all of them at once.
+    pub fn validate(&self) -> Vec<AstError> {
+        let mut errors = Parser::errors(&self.ast);
+        for component in self.components.values() {
+            errors.extend(Parser::errors(component.ast()));
+        }
+        if let Some(ref locales) = self.locales {
+            for components in locales.values() {
+                for component in components.values() {
+                    errors.extend(Parser::errors(component.ast()));
+                }
+            }
+        }
+
+        errors.sort_by(|a, b| {
+            let location = |error: &AstError| {
+                error.span().map(|span| (span.file.clone(), span.start))
+            };
+            location(a).cmp(&location(b))
+        });
+        errors
+    }
+
+    /// Checks every locale against the locale `reference`, finding the components a locale
+    /// lacks, the ones only it has, and the ones whose parameters differ from the reference's.
+    /// A component a locale lacks isn't missing if a more general locale it falls back to has
+    /// it, so "de-AT" only needs the components that differ from "de". Returns `NoSuchLocale` if
+    /// the template has no locale `reference`.
+    pub fn check_locales(&self, reference: &str) -> Result<Vec<LocaleError>, TemplateError> {
+        let locales = match self.locales {
+            Some(ref locales) => locales,
+            None => return Err(TemplateError::NoSuchLocale(reference.to_owned())),
+        };
+        let normalise = |locale: &str| locale.replace('_', "-").to_lowercase();
+        let reference_name = match locales.keys().find(|l| normalise(l) == normalise(reference)) {
+            Some(locale) => locale,
+            None => return Err(TemplateError::NoSuchLocale(reference.to_owned())),
+        };
+        let reference = &locales[reference_name];
+
+        let mut names: Vec<&String> = locales.keys().filter(|l| *l != reference_name).collect();
+        names.sort();
+
+        let mut errors = Vec::new();
+        for lang in names {
+            // Only the locales that are the same as, or more general than, this one count, as
+            // falling back to the default locale is what this is checking for.
+            let chain: Vec<&HashMap<String, Arc<Component>>> = self.locale_chain(lang)
+                .into_iter()
+                .filter(|locale| {
+                    let (lang, locale) = (normalise(lang), normalise(locale));
+                    lang == locale || lang.starts_with(&format!("{}-", locale))
+                })
+                .map(|locale| &locales[locale])
+                .collect();
+            let components = &locales[lang];
+
+            let mut expected: Vec<&Arc<Component>> = reference.values().collect();
+            expected.sort_by(|a, b| a.name().cmp(b.name()));
+            for component in expected {
+                let name = component.name();
+                match chain.iter().filter_map(|locale| locale.get(name)).next() {
+                    Some(found) => {
+                        let (params, found_params) = (parameters(component), parameters(found));
+                        if components.contains_key(name) && params != found_params {
+                            errors.push(LocaleError::MismatchedArguments(lang.clone(),
+                                                                         name.to_owned(),
+                                                                         params,
+                                                                         found_params,
+                                                                         found.span().clone()));
+                        }
+                    }
+                    None => {
+                        errors.push(LocaleError::MissingComponent(lang.clone(),
+                                                                  name.to_owned(),
+                                                                  component.span().clone()));
+                    }
+                }
+            }
+
+            let mut extra: Vec<&Arc<Component>> = components.values()
+                .filter(|component| !reference.contains_key(component.name()))
+                .collect();
+            extra.sort_by(|a, b| a.name().cmp(b.name()));
+            for component in extra {
+                errors.push(LocaleError::ExtraComponent(lang.clone(),
+                                                        component.name().to_owned(),
+                                                        component.span().clone()));
+            }
+        }
+        Ok(errors)
+    }
+
+    /// Renders the template into a HTML String, in the locale `lang`, with `json` as the
+    /// variables.
+    pub fn render(&self,
+                  lang: &str,
+                  json: &BTreeMap<String, Value>)
+                  -> Result<String, TemplateError> {
+        let mut html = Vec::new();
+
+        if let Err(error) = self.render_to(lang, json, &mut html) {
+            return Err(error);
+        }
+
+        match String::from_utf8(html) {
+            Ok(html) => Ok(html),
+            Err(error) => Err(TemplateError::CodegenError(CodegenError::FromUtf8Error(error))),
+        }
+    }
+
+    /// Renders the template, writing the HTML into `writer` as it is generated, instead of
+    /// building the whole document in memory first.
+    pub fn render_to<W: io::Write>(&self,
+                                   lang: &str,
+                                   json: &BTreeMap<String, Value>,
+                                   writer: &mut W)
+                                   -> Result<(), TemplateError> {
+        if self.locales.is_some() && self.locale_chain(lang).is_empty() {
+            return Err(TemplateError::NoSuchLocale(String::from(lang)));
+        }
+
+        match Codegen::new(self, lang, json).write_html(&self.ast, writer) {
+            Ok(()) => Ok(()),
+            Err(error) => Err(TemplateError::CodegenError(error)),
+        }
+    }
+
+    /// Renders the template, writing the HTML into a `fmt::Write` such as a `String`, or a
+    /// `fmt::Formatter`.
+    pub fn render_fmt<W: fmt::Write>(&self,
+                                     lang: &str,
+                                     json: &BTreeMap<String, Value>,
+                                     writer: &mut W)
+                                     -> Result<(), TemplateError> {
+        self.render_to(lang, json, &mut FmtWriter(writer))
+    }
+}
+
+/// Adapts a `fmt::Write` into an `io::Write`. Codegen only ever writes whole strings, so each
+/// buffer written is always valid UTF-8.
+struct FmtWriter<'a, W: fmt::Write + 'a>(&'a mut W);
+
+impl<'a, W: fmt::Write> io::Write for FmtWriter<'a, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let text = match str::from_utf8(buf) {
+            Ok(text) => text,
+            Err(_) => return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid UTF-8")),
+        };
+
+        match self.0.write_str(text) {
+            Ok(()) => Ok(buf.len()),
+            Err(_) => Err(io::Error::new(io::ErrorKind::Other, "formatter error")),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Errors relating to the templating rendering.
+#[derive(Debug)]
 pub enum TemplateError {
     /// Error within the Code generation.
     CodegenError(CodegenError),
+    /// A locale's catalog couldn't be converted into components by its loader.
+    InvalidCatalog(PathBuf, String),
+    /// The template has no locale of that name, or any locale it falls back to.
+    NoSuchLocale(String),
     /// The component called already exists.
     PreDefinedComponent,
+    /// The filter registered already exists.
+    PreDefinedFilter,
+    /// The loader registered already exists.
+    PreDefinedLoader,
     /// The function called already exists.
     PreDefinedFunction,
     /// Any IO errors, from the methods.
     IoError(io::Error),
 }
+
+/// A difference between a locale, and the locale it's checked against with
+/// `CompiledTemplate::check_locales`. Each has the name of the locale, and of the component.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LocaleError {
+    /// Neither the locale, nor a more general locale it falls back to has a component the
+    /// reference locale has. The span is where the reference locale defines it.
+    MissingComponent(String, String, Span),
+    /// The locale has a component the reference locale doesn't.
+    ExtraComponent(String, String, Span),
+    /// The component takes different parameters in the locale, than in the reference locale.
+    /// Has the parameters in the reference locale, and then in the locale, with `&` before the
+    /// ones taking components.
+    MismatchedArguments(String, String, Vec<String>, Vec<String>, Span),
+}
+
+/// The parameters of a component, with `&` before the ones taking components.
+fn parameters(component: &Component) -> Vec<String> {
+    component.args()
+             .iter()
+             .map(|arg| {
+                 match *arg {
+                     ArgKey::Comp(ref name) => format!("&{}", name),
+                     ref arg => arg.value(),
+                 }
+             })
+             .collect()
+}
+
+/// Lexes the Polly a loader generated from the catalog at `path`. The generated source isn't what
+/// the catalog holds, so each top-level component is lexed on its own, as the file
+/// "<path>#<name>", and errors in it are reported against the message they came from, without a
+/// snippet of lines the catalog doesn't have.
+fn lex_catalog(source: &str, path: &Path) -> Vec<Lexeme> {
+    let mut starts = Vec::new();
+    let mut depth: usize = 0;
+    let mut lexemes = Lexer::with_file(source, path).output().into_iter();
+    while let Some(lexeme) = lexemes.next() {
+        match lexeme {
+            Lexeme::Symbol(_, Operator::BackSlash) => {
+                let _ = lexemes.next();
+            }
+            Lexeme::Symbol(_, Operator::OpenBrace) => depth += 1,
+            Lexeme::Symbol(_, Operator::CloseBrace) => depth = depth.saturating_sub(1),
+            Lexeme::Symbol(ref span, Operator::Ampersand) if depth == 0 => starts.push(span.start),
+            _ => {}
+        }
+    }
+
+    let mut output = Vec::new();
+    for (index, &start) in starts.iter().enumerate() {
+        let end = starts.get(index + 1).map_or(source.len(), |&end| end);
+        let name: String = source[start + 1..end]
+                               .chars()
+                               .take_while(|&ch| !ch.is_whitespace() && ch != '(' && ch != '{')
+                               .collect();
+        let file = PathBuf::from(format!("{}#{}", path.display(), name));
+        output.extend(Lexer::with_file(&source[start..end], file).output());
+    }
+    output
+}
+
+/// Places each component behind an `Arc`, so they can be shared between threads, and passed to
+/// functions without copying their AST.
+fn share_components(components: HashMap<String, Component>) -> HashMap<String, Arc<Component>> {
+    components.into_iter().map(|(name, component)| (name, Arc::new(component))).collect()
+}
+
+/// Whether two values are equal, with numbers compared by their value, so `1` is equal to `1.0`.
+fn json_eq(left: &Value, right: &Value) -> bool {
+    match (left.as_f64(), right.as_f64()) {
+        (Some(left), Some(right)) => left == right,
+        _ => left == right,
+    }
+}
+
+/// Orders two numbers, or two strings.
+fn json_cmp(left: &Value, right: &Value) -> Result<Ordering, String> {
+    match (left, right) {
+        (&Value::String(ref left), &Value::String(ref right)) => Ok(left.cmp(right)),
+        _ => {
+            let ordering = match (left.as_f64(), right.as_f64()) {
+                (Some(left), Some(right)) => left.partial_cmp(&right),
+                _ => None,
+            };
+            ordering.ok_or(format!("Only numbers, or strings can be compared, not {:?}, and {:?}",
+                                   left,
+                                   right))
+        }
+    }
+}
+
 fn json_into_bool(json: &Value) -> bool {
     match *json {
         Value::Array(ref array) => !array.is_empty(),
@@ -568,10 +1657,14 @@ fn json_into_bool(json: &Value) -> bool {
 }
 #[allow(dead_code, unused_imports)]
 mod tests {
-    use super::Template;
+    use super::{LocaleError, Template, TemplateError};
+    use compiler::{ArgValue, AstError, Codegen, CodegenError};
     use std::fs::File;
+    use std::io;
     use std::io::Read;
     use std::collections::BTreeMap;
+    use std::sync::Arc;
+    use std::thread;
     use serde_json;
     use serde_json::Value;
 
@@ -598,6 +1691,77 @@ mod tests {
         assert_eq!(template.unwrap(), BASIC);
     }
 
+    #[test]
+    fn component_arguments() {
+        let json: Value = serde_json::from_str(r#"{"heading": "Welcome"}"#).unwrap();
+        let card = "<div class=\"card \"><h2>Welcome</h2><p>Hello World!</p></div>";
+        let expected = format!("<html><body>{}<section>{}</section></body></html>", card, card);
+
+        let template = Template::load("./tests/component_arguments.polly")
+                           .unwrap()
+                           .no_locales()
+                           .json(json.as_object().unwrap().to_owned())
+                           .render("en");
+        assert_eq!(template.unwrap(), expected);
+    }
+
+    #[test]
+    fn component_children() {
+        let json: Value = serde_json::from_str(r#"{"heading": "Welcome", "name": "Joe"}"#)
+                              .unwrap();
+        let expected = "<html><body><main><div class=\"panel \"><h2>Welcome</h2><p>Hello \
+                        Joe!</p><footer>Joe</footer></div></main><section><main><p>Joe</p></main>\
+                        </section><main></main></body></html>";
+
+        let template = Template::load("./tests/component_children.polly")
+                           .unwrap()
+                           .no_locales()
+                           .json(json.as_object().unwrap().to_owned())
+                           .render("en");
+        assert_eq!(template.unwrap(), expected);
+    }
+
+    #[test]
+    fn component_defaults() {
+        let json: Value = serde_json::from_str(r#"{"save": "Save", "cancel": "Cancel"}"#)
+                              .unwrap();
+        let expected = "<div><button class=\"primary\">Save 2</button><button \
+                        class=\"secondary\">Cancel 2</button><button class=\"primary\">Save \
+                        3</button><p><button class=\"Cancel\">Save 2</button></p></div>";
+
+        let template = Template::load("./tests/component_defaults.polly")
+                           .unwrap()
+                           .no_locales()
+                           .json(json.as_object().unwrap().to_owned())
+                           .render("en");
+        assert_eq!(template.unwrap(), expected);
+    }
+
+    #[test]
+    fn component_argument_errors() {
+        use compiler::CodegenError::{MissingArgument, UnknownArgument};
+        let button = "&button(@label, @kind = \"primary\") {/button(class=@kind) {@label}}\n";
+        let render = |call: &str| {
+            Template::load_from_source("button.polly", format!("{}{}", button, call))
+                .no_locales()
+                .render("en")
+        };
+
+        match render("&button(kind = \"secondary\")") {
+            Err(TemplateError::CodegenError(MissingArgument(ref name, _))) => {
+                assert_eq!(name, "label")
+            }
+            result => panic!("Expected a missing argument, found {:?}", result),
+        }
+
+        match render("&button(@label, colour = \"red\")") {
+            Err(TemplateError::CodegenError(UnknownArgument(ref name, _))) => {
+                assert_eq!(name, "colour")
+            }
+            result => panic!("Expected an unknown argument, found {:?}", result),
+        }
+    }
+
     #[test]
     fn component_imported() {
         let mut template = Template::load("./tests/component_import.polly").unwrap().no_locales();
@@ -628,6 +1792,238 @@ mod tests {
         assert_eq!(template.unwrap_render("en"), BASIC);
     }
 
+    #[test]
+    fn variables_are_escaped_unless_raw() {
+        let expected = "<!DOCTYPE html><html><body><p>&lt;script&gt;alert(&quot;Hello \
+                        World!&quot;)&lt;/script&gt;</p><div><p>Hello World!</p></div></body>\
+                        </html>";
+        let json: Value = serde_json::from_str(r#"{
+            "text": "<script>alert(\"Hello World!\")</script>",
+            "raw": "<p>Hello World!</p>"
+        }"#)
+                              .unwrap();
+
+        assert_eq!(Template::load("./tests/escaping.polly")
+                       .unwrap()
+                       .no_locales()
+                       .json(json.as_object().unwrap().to_owned())
+                       .unwrap_render("en"),
+                   expected);
+    }
+
+    #[test]
+    fn variable_followed_by_text() {
+        let json: Value = serde_json::from_str(r#"{"object": {"world": "World"}}"#).unwrap();
+
+        let template = Template::load_from_source("variable_followed_by_text",
+                                                  "/p{Hello @object.world !}")
+                           .no_locales()
+                           .json(json.as_object().unwrap().to_owned());
+        assert_eq!(template.unwrap_render("en"), "<p>Hello World !</p>");
+    }
+
+    #[test]
+    fn string_literals() {
+        let expected = "<p title=\"  Two  spaces, and a / \">Don't \"quote\" me</p><p \
+                        title=\"Say &quot;hi&quot;\"></p><p title=\"It&#39;s \
+                        &quot;escaped&quot;\u{2764}\"></p><p>Tag #x('it\\'s' \"a\\nb\") \
+                        end</p>";
+
+        assert_eq!(Template::load("./tests/strings.polly")
+                       .unwrap()
+                       .no_locales()
+                       .unwrap_render("en"),
+                   expected);
+    }
+
+    /// Writes out the JSON arguments passed to it.
+    fn show_arguments(args: BTreeMap<String, ArgValue>, _: &Codegen) -> Result<String, String> {
+        let args: Vec<String> = args.iter()
+                                    .map(|(key, value)| {
+                                        match *value {
+                                            ArgValue::Json(Some(ref json)) => {
+                                                format!("{}={}",
+                                                        key,
+                                                        serde_json::to_string(json).unwrap())
+                                            }
+                                            _ => format!("{}=?", key),
+                                        }
+                                    })
+                                    .collect();
+        Ok(args.join(" "))
+    }
+
+    #[test]
+    fn literal_function_arguments() {
+        let source = r#"$show(string = "Hi, there", number = -1.5, bool = true, null = null)"#;
+        let mut template = Template::load_from_source("literals", source).no_locales();
+        template.register(String::from("show"), Box::new(show_arguments)).unwrap();
+
+        assert_eq!(template.unwrap_render("en"),
+                   r#"bool=true null=null number=-1.5 string="Hi, there""#);
+    }
+
+    #[test]
+    fn call_arguments() {
+        let json: Value = serde_json::from_str(r#"{
+            "user": {"name": "Jane"},
+            "users": [{"name": "Joe"}, {"name": "Ann"}]
+        }"#)
+                              .unwrap();
+        let expected = "<div><span>Jane is admin aged 30</span><p><span>Ann is guest aged \
+                        </span></p>count=2 first={\"name\":\"Joe\"} name=\"Joe\"</div>";
+
+        let mut template = Template::load("./tests/call_arguments.polly")
+                               .unwrap()
+                               .no_locales()
+                               .json(json.as_object().unwrap().to_owned());
+        template.register(String::from("show"), Box::new(show_arguments)).unwrap();
+
+        assert_eq!(template.unwrap_render("en"), expected);
+    }
+
+    #[test]
+    fn variable_paths() {
+        let json: Value = serde_json::from_str(r#"{
+            "items": [{"name": "First"}, {"name": "Last", "tags": ["new"]}],
+            "map": {"key.with.dots": "Dots", "a]b": "Bracket"}
+        }"#)
+                              .unwrap();
+        let expected = "<ul><li>First</li><li>Last</li><li>Dots</li><li>new</li><li \
+                        title=\"Bracket\">[]</li></ul>";
+
+        let template = Template::load("./tests/variable_paths.polly")
+                           .unwrap()
+                           .no_locales()
+                           .json(json.as_object().unwrap().to_owned());
+        assert_eq!(template.unwrap_render("en"), expected);
+
+        let source = "@items[0].name.first";
+        let template = Template::load_from_source("paths", source)
+                           .no_locales()
+                           .json(json.as_object().unwrap().to_owned());
+        match template.render("en") {
+            Err(TemplateError::CodegenError(CodegenError::NotAnObjectOrNull(ref name, _))) => {
+                assert_eq!(name, "items[0].name.first")
+            }
+            result => panic!("Expected a path into a string to fail, found {:?}", result),
+        }
+    }
+
+    #[test]
+    fn filters() {
+        let json: Value = serde_json::from_str(r#"{
+            "name": "Polly Template",
+            "query": "a&b c",
+            "tags": ["HTML", "JSON"]
+        }"#)
+                              .unwrap();
+        let expected = "<ul><li>POLLY...</li><li>html, json</li><li>Anonymous</li><li>2 \
+                        tags</li><li title=\"a%26b%20c\">[\"HTML\",\"JSON\"]</li><li>Polly \
+                        Template!</li></ul>";
+
+        let mut template = Template::load("./tests/filters.polly")
+                               .unwrap()
+                               .no_locales()
+                               .strict(true)
+                               .json(json.as_object().unwrap().to_owned());
+        template.register_filter(String::from("shout"), Box::new(|value, _| {
+                    Ok(Value::String(format!("{}!", value.as_string().unwrap_or(""))))
+                }))
+                .unwrap();
+        assert_eq!(template.unwrap_render("en"), expected);
+
+        let template = Template::load_from_source("filters", "@name | reverse")
+                           .no_locales()
+                           .json(json.as_object().unwrap().to_owned());
+        match template.render("en") {
+            Err(TemplateError::CodegenError(CodegenError::NoSuchFilter(ref name, _))) => {
+                assert_eq!(name, "reverse")
+            }
+            result => panic!("Expected a missing filter, found {:?}", result),
+        }
+    }
+
+    #[test]
+    fn undefined_filters() {
+        let mut template = Template::load_from_source("filters", "@missing | none")
+                               .no_locales()
+                               .strict(true);
+        template.register_undefined_filter(String::from("none"), Box::new(|value, _| {
+                    match value {
+                        Value::Null => Ok(Value::String(String::from("None"))),
+                        value => Ok(value),
+                    }
+                }))
+                .unwrap();
+        assert_eq!(template.unwrap_render("en"), "None");
+
+        let mut template = Template::load_from_source("filters", "@missing | shout")
+                               .no_locales()
+                               .strict(true);
+        template.register_filter(String::from("shout"), Box::new(|value, _| Ok(value))).unwrap();
+        match template.render("en") {
+            Err(TemplateError::CodegenError(CodegenError::UndefinedVariable(ref name, _))) => {
+                assert_eq!(name, "missing")
+            }
+            result => panic!("Expected an undefined variable, found {:?}", result),
+        }
+    }
+
+    #[test]
+    fn strict() {
+        use std::sync::Mutex;
+        let warnings = Arc::new(Mutex::new(Vec::new()));
+        let collected = warnings.clone();
+
+        let html = Template::load("./tests/strict.polly")
+                       .unwrap()
+                       .no_locales()
+                       .on_warning(Box::new(move |warning| {
+                           collected.lock().unwrap().push(warning.span().unwrap().line)
+                       }))
+                       .unwrap_render("en");
+        assert_eq!(html, "<div><p>Hello !</p></div>");
+        assert_eq!(*warnings.lock().unwrap(), vec![6, 7]);
+
+        let result = Template::load("./tests/strict.polly")
+                         .unwrap()
+                         .no_locales()
+                         .strict(true)
+                         .render("en");
+        match result {
+            Err(TemplateError::CodegenError(CodegenError::UndefinedVariable(ref name, _))) => {
+                assert_eq!(name, "user.name")
+            }
+            result => panic!("Expected an undefined variable, found {:?}", result),
+        }
+    }
+
+    #[test]
+    fn attributes_from_variables() {
+        let expected = "<!DOCTYPE html><html><body><a class=\"link\" href=\"/users/1?tab=posts&amp;\
+                        page=2\">Jane</a><a href=\"about:invalid#polly\">Website</a><a \
+                        href=\"about:invalid#polly\">Raw</a><img \
+                        title=\"&quot;&gt;&lt;script&gt;\"><input></body></html>";
+        let json: Value = serde_json::from_str(r#"{
+            "profile": {
+                "name": "Jane",
+                "url": "/users/1?tab=posts&page=2",
+                "website": "javascript:alert(1)",
+                "bio": "\"><script>",
+                "locked": false
+            }
+        }"#)
+                              .unwrap();
+
+        assert_eq!(Template::load("./tests/attributes.polly")
+                       .unwrap()
+                       .no_locales()
+                       .json(json.as_object().unwrap().to_owned())
+                       .unwrap_render("en"),
+                   expected);
+    }
+
     #[test]
     fn function_each() {
         let expected = "<!DOCTYPE \
@@ -645,6 +2041,46 @@ mod tests {
 
     }
 
+    #[test]
+    fn function_each_loop() {
+        let expected = "<ul><li data-first>1 of 2: Rust</li><li>2 of 2: C++</li></ul><p><b>Rust</b>\
+                        <hr><b>C++</b></p><dl><dt>size</dt><dd>12</dd><dt>theme</dt><dd>dark</dd>\
+                        </dl><ul><li>Nothing here</li></ul>";
+        let json: Value = serde_json::from_str(r#"{
+            "languages": ["Rust", "C++"],
+            "settings": {"theme": "dark", "size": 12}
+        }"#)
+                              .unwrap();
+
+        assert_eq!(Template::load("./tests/function_each_loop.polly")
+                       .unwrap()
+                       .no_locales()
+                       .json(json.as_object().unwrap().to_owned())
+                       .unwrap_render("en"),
+                   expected);
+    }
+
+    #[test]
+    fn function_compare() {
+        let expected = "<div><span class=\"badge \">Admin</span>AdultMinor<span class=\"badge \
+                        \">Admin</span><p class=\"green \">active</p><p class=\"grey \
+                        \">Unknown</p></div>";
+        let json: Value = serde_json::from_str(r#"{
+            "account": {"status": "active"},
+            "age": 18,
+            "role": "admin",
+            "tags": ["polly", "rust"]
+        }"#)
+                              .unwrap();
+
+        assert_eq!(Template::load("./tests/function_compare.polly")
+                       .unwrap()
+                       .no_locales()
+                       .json(json.as_object().unwrap().to_owned())
+                       .unwrap_render("en"),
+                   expected);
+    }
+
     #[test]
     fn function_if() {
         let json: Value = serde_json::from_str(r#"{"condition": true, "text": "Hello World!"}"#)
@@ -692,4 +2128,322 @@ mod tests {
                    BASIC_DE);
     }
 
+    #[test]
+    fn locale_fallback() {
+        let json: BTreeMap<String, Value> = BTreeMap::new();
+        let template = Template::load("./tests/fallback.polly")
+                           .unwrap()
+                           .locales_dir("./tests/fallback_locales/")
+                           .default_locale("en")
+                           .compile()
+                           .unwrap();
+
+        assert_eq!(template.locale_chain("de-AT"), vec!["de-AT", "de", "en"]);
+        assert_eq!(template.locale_chain("de_at-x-vienna"), vec!["de-AT", "de", "en"]);
+        assert_eq!(template.render("de-AT", &json).unwrap(),
+                   "<p>Servus</p><p>Auf Wiedersehen</p>");
+        assert_eq!(template.render("de-CH", &json).unwrap(), "<p>Hallo</p><p>Auf Wiedersehen</p>");
+        assert_eq!(template.render("fr", &json).unwrap(), "<p>Hello</p><p>Goodbye</p>");
+
+        let template = Template::load("./tests/fallback.polly")
+                           .unwrap()
+                           .locales_dir("./tests/fallback_locales/")
+                           .default_locale("en")
+                           .locale_fallback(false)
+                           .compile()
+                           .unwrap();
+        assert_eq!(template.render("de-AT", &json).unwrap(), "<p>Servus</p><p></p>");
+        match template.render("de-CH", &json) {
+            Err(TemplateError::NoSuchLocale(ref lang)) => assert_eq!(lang, "de-CH"),
+            result => panic!("Expected a missing locale, found {:?}", result),
+        }
+    }
+
+    #[test]
+    fn locales_without_file_name() {
+        let template = Template::load_from_source("..", "/p {}").locales_dir("./tests/locales/");
+        match template.compile() {
+            Err(TemplateError::IoError(ref error)) => {
+                assert_eq!(error.kind(), io::ErrorKind::InvalidInput)
+            }
+            result => panic!("Expected an IO error, found {:?}", result.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn broken_catalog_fails_every_locale() {
+        let mut template = Template::load("./tests/loader.polly")
+                               .unwrap()
+                               .locales_dir("./tests/loader_locales/");
+        template.register_loader(String::from("txt"),
+                                 Box::new(|_, _| Err(String::from("bad catalog"))))
+                .unwrap();
+
+        match template.compile() {
+            Err(TemplateError::InvalidCatalog(ref path, ref message)) => {
+                assert!(path.ends_with("en/loader.txt"));
+                assert_eq!(message, "bad catalog");
+            }
+            result => panic!("Expected an invalid catalog, found {:?}", result.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn check_locales() {
+        let template = Template::load("./tests/check.polly")
+                           .unwrap()
+                           .locales_dir("./tests/check_locales/")
+                           .compile()
+                           .unwrap();
+
+        let errors = template.check_locales("en").unwrap();
+        assert_eq!(errors.len(), 3);
+        match errors[0] {
+            LocaleError::MissingComponent(ref lang, ref name, ref span) => {
+                assert_eq!((&**lang, &**name), ("de", "farewell"));
+                assert!(span.file.as_ref().unwrap().ends_with("en/check.polly"));
+            }
+            ref error => panic!("Expected a missing component, found {:?}", error),
+        }
+        match errors[1] {
+            LocaleError::MismatchedArguments(ref lang, ref name, ref expected, ref found, _) => {
+                assert_eq!((&**lang, &**name), ("de", "items"));
+                assert_eq!(expected, &["count"]);
+                assert_eq!(found, &["count", "unit"]);
+            }
+            ref error => panic!("Expected mismatched arguments, found {:?}", error),
+        }
+        match errors[2] {
+            LocaleError::ExtraComponent(ref lang, ref name, _) => {
+                assert_eq!((&**lang, &**name), ("de", "title"));
+            }
+            ref error => panic!("Expected an extra component, found {:?}", error),
+        }
+
+        match template.check_locales("es") {
+            Err(TemplateError::NoSuchLocale(ref lang)) => assert_eq!(lang, "es"),
+            result => panic!("Expected a missing locale, found {:?}", result),
+        }
+    }
+
+    #[test]
+    fn formatting() {
+        let json: Value = serde_json::from_str(r#"{"total": 1234567.891, "share": 0.125,
+                                                   "price": "9.5", "currency": "USD",
+                                                   "day": "2016-03-04", "timestamp": 0}"#)
+                              .unwrap();
+        let template = Template::load("./tests/format.polly")
+                           .unwrap()
+                           .no_locales()
+                           .compile()
+                           .unwrap();
+        let render = |lang| template.render(lang, json.as_object().unwrap()).unwrap();
+
+        assert_eq!(render("en"),
+                   "<p>1,234,567.891</p><p>12.5%</p><p>$9.50</p><p>Friday, March 4, 2016</p>\
+                    <p>1 Jan 1970</p>");
+        assert_eq!(render("fr-CA"),
+                   "<p>1\u{202f}234\u{202f}567,891</p><p>12,5\u{a0}%</p><p>9,50\u{a0}$</p>\
+                    <p>vendredi 4 mars 2016</p><p>1 janv. 1970</p>");
+        assert_eq!(render("ja"),
+                   "<p>1,234,567.891</p><p>12.5%</p><p>$9.50</p><p>2016年3月4日金曜日</p>\
+                    <p>1 1月 1970</p>");
+    }
+
+    #[test]
+    fn plurals() {
+        let template = Template::load("./tests/plural.polly")
+                           .unwrap()
+                           .locales_dir("./tests/plural_locales/")
+                           .strict(true)
+                           .compile()
+                           .unwrap();
+        let render = |lang: &str, json: &str| {
+            let json: Value = serde_json::from_str(json).unwrap();
+            template.render(lang, json.as_object().unwrap())
+        };
+
+        assert_eq!(render("en", r#"{"count": 0, "name": "Ann", "gender": "female"}"#).unwrap(),
+                   "<p>No items</p><p>She invited Ann</p>");
+        assert_eq!(render("en", r#"{"count": 1, "name": "Joe", "gender": "male"}"#).unwrap(),
+                   "<p>One item</p><p>He invited Joe</p>");
+        assert_eq!(render("en", r#"{"count": 5, "name": "Sam", "gender": null}"#).unwrap(),
+                   "<p>5 items</p><p>They invited Sam</p>");
+        assert_eq!(render("ru", r#"{"count": 22, "name": "Анна", "gender": "female"}"#).unwrap(),
+                   "<p>22 предмета</p><p>Она пригласила Анна</p>");
+        assert_eq!(render("ru", r#"{"count": 11, "name": "Иван", "gender": "male"}"#).unwrap(),
+                   "<p>11 предметов</p><p>Он пригласил Иван</p>");
+
+        match render("ru", r#"{"count": 1.5, "name": "Иван", "gender": "male"}"#) {
+            Err(TemplateError::CodegenError(CodegenError::MissingCase(ref name, _))) => {
+                assert_eq!(name, "other")
+            }
+            result => panic!("Expected a missing case, found {:?}", result),
+        }
+    }
+
+    #[test]
+    fn catalogs() {
+        let template = Template::load("./tests/catalog.polly")
+                           .unwrap()
+                           .locales_dir("./tests/catalog_locales/")
+                           .compile()
+                           .unwrap();
+        let json: Value = serde_json::from_str(r#"{
+            "name": "Ann",
+            "count": 3,
+            "page": "Home",
+            "site": "Polly"
+        }"#)
+                              .unwrap();
+        let json = json.as_object().unwrap();
+
+        assert_eq!(template.render("en", json).unwrap(),
+                   "<p>Hello Ann!</p><p>3 items</p><p>Home | Polly</p>");
+        assert_eq!(template.render("de", json).unwrap(),
+                   "<p>Hallo Ann!</p><p>3 Artikel</p><p>Home | Polly</p>");
+        assert_eq!(template.render("ru", json).unwrap(),
+                   "<p>Привет, Ann!</p><p>3 товара</p><p>Home | Polly</p>");
+    }
+
+    #[test]
+    fn catalog_errors_point_at_their_message() {
+        let mut template = Template::load("./tests/loader.polly")
+                               .unwrap()
+                               .locales_dir("./tests/loader_locales/");
+        template.register_loader(String::from("txt"),
+                                 Box::new(|_, _| {
+                                     Ok(String::from("&good {Fine}\n&bad {/p(title=\"x) {y}}\n"))
+                                 }))
+                .unwrap();
+        let errors = template.compile().unwrap().validate();
+
+        assert_eq!(errors.len(), 1);
+        let span = errors[0].span().unwrap();
+        assert_eq!(span.to_string(), "./tests/loader_locales/en/loader.txt#bad:1:16");
+    }
+
+    #[test]
+    fn compiled_template() {
+        let json: BTreeMap<String, Value> = BTreeMap::new();
+        let template = Template::load("./tests/locales.polly")
+                           .unwrap()
+                           .locales_dir("./tests/locales/")
+                           .compile()
+                           .unwrap();
+
+        assert_eq!(template.render("en", &json).unwrap(), BASIC);
+        assert_eq!(template.render("de", &json).unwrap(), BASIC_DE);
+        assert_eq!(template.render("en", &json).unwrap(), BASIC);
+        assert!(template.render("fr", &json).is_err());
+    }
+
+    #[test]
+    fn render_to_writer() {
+        let template = Template::load("./tests/element.polly")
+                           .unwrap()
+                           .no_locales()
+                           .compile()
+                           .unwrap();
+        let mut html = Vec::new();
+        template.render_to("en", &BTreeMap::new(), &mut html).unwrap();
+        assert_eq!(String::from_utf8(html).unwrap(), BASIC);
+
+        let mut html = String::new();
+        template.render_fmt("en", &BTreeMap::new(), &mut html).unwrap();
+        assert_eq!(html, BASIC);
+    }
+
+    #[test]
+    fn compiled_template_shared_between_threads() {
+        let template = Arc::new(Template::load("./tests/locales.polly")
+                                    .unwrap()
+                                    .locales_dir("./tests/locales/")
+                                    .compile()
+                                    .unwrap());
+
+        let handles: Vec<_> = ["en", "de", "en", "de"]
+                                  .iter()
+                                  .map(|lang| {
+                                      let template = template.clone();
+                                      thread::spawn(move || {
+                                          template.render(lang, &BTreeMap::new()).unwrap()
+                                      })
+                                  })
+                                  .collect();
+
+        let rendered: Vec<String> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+        assert_eq!(rendered, vec![BASIC, BASIC_DE, BASIC, BASIC_DE]);
+    }
+
+    #[test]
+    fn comments() {
+        let template = Template::load("./tests/comments.polly").unwrap().no_locales();
+        assert_eq!(template.unwrap_render("en"),
+                   r#"<html><body class="page"><p>Hello World!</p></body></html>"#);
+
+        let template = Template::load("./tests/comments.polly")
+                           .unwrap()
+                           .no_locales()
+                           .html_comments(true);
+        assert_eq!(template.unwrap_render("en"),
+                   "<!-- The page, which is the only thing in this file. --><html><body \
+                    class=\"page\"><p>Hello <!-- Not - - rendered - -> -->World!</p></body>\
+                    </html>");
+    }
+
+    #[test]
+    fn every_parse_error_is_reported() {
+        let template = Template::load("./tests/errors.polly")
+                           .unwrap()
+                           .no_locales()
+                           .compile()
+                           .unwrap();
+        let lines: Vec<usize> = template.validate()
+                                        .iter()
+                                        .map(|error| error.span().unwrap().line)
+                                        .collect();
+
+        assert_eq!(lines, vec![2, 5, 7, 10]);
+    }
+
+    #[test]
+    fn errors_point_at_their_source() {
+        let error = Template::load_from_source("./tests/spans.polly", "/p {\n    &missing()\n}")
+                        .no_locales()
+                        .render("en")
+                        .unwrap_err();
+
+        let span = match error {
+            TemplateError::CodegenError(ref error) => error.span().unwrap().clone(),
+            _ => panic!("Expected a codegen error, got: {:?}", error),
+        };
+        assert_eq!((span.line, span.col), (2, 6));
+        assert_eq!(span.to_string(), "./tests/spans.polly:2:6");
+    }
+
+    #[test]
+    fn unterminated_strings_are_errors() {
+        let validate = |source: &str| {
+            Template::load_from_source("strings.polly", source)
+                .no_locales()
+                .compile()
+                .unwrap()
+                .validate()
+        };
+
+        let errors = validate("/p(title=\"abc) {Hello} /div {x}");
+        assert_eq!(errors.len(), 1);
+        match errors[0] {
+            AstError::UnterminatedString(ref span) => {
+                assert_eq!((span.start, span.end, span.col), (9, 10, 10))
+            }
+            ref error => panic!("Expected an unterminated string, found {:?}", error),
+        }
+
+        let errors = validate("/div {\n    /p(title='abc) {Hello}\n}");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].span().map(|span| (span.line, span.col)), Some((2, 14)));
+    }
+
 }
@@ -0,0 +1,7 @@
+# German translations
+hello = Hallo { $name }!
+items = { $count ->
+    [one] Ein Artikel
+   *[other] { $count } Artikel
+}
+title = { $page } | { $site }